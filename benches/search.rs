@@ -0,0 +1,137 @@
+use answerkey_search::{solve_constrained, AnswerKeySet, Choices, QuizAttempt, DEFAULT_CHOICES};
+use criterion::measurement::WallTime;
+use criterion::{
+    criterion_group, criterion_main, BatchSize, BenchmarkGroup, BenchmarkId, Criterion,
+};
+
+const LEN: usize = 25;
+const MISTAKES: usize = 4;
+
+fn letter(i: usize) -> char {
+    ['A', 'B', 'C', 'D'][i % 4]
+}
+
+// A fixed key, and attempts that differ from it in a reproducible pattern.
+fn key(len: usize) -> Vec<char> {
+    (0..len).map(|q| letter(q * 3 + q / 4)).collect()
+}
+
+fn top_attempt(len: usize, mistakes: usize) -> QuizAttempt {
+    let answers: String = key(len)
+        .iter()
+        .enumerate()
+        .map(|(q, &c)| {
+            if q % (len / mistakes) == 0 && q / (len / mistakes) < mistakes {
+                letter(c as usize + 1)
+            } else {
+                c
+            }
+        })
+        .collect();
+    QuizAttempt::from_string(&answers, (len - mistakes) as f64)
+}
+
+fn other_attempts(len: usize) -> Vec<QuizAttempt> {
+    let key = key(len);
+    (0..8)
+        .map(|i| {
+            let answers: String = (0..len).map(|q| letter(q * 7 + i * 3 + q % 3)).collect();
+            let score = answers.chars().zip(&key).filter(|(a, k)| a == *k).count();
+            QuizAttempt::from_string(&answers, score as f64)
+        })
+        .collect()
+}
+
+fn generate(c: &mut Criterion) {
+    let attempt = top_attempt(LEN, MISTAKES);
+    let choices = Choices::uniform(&DEFAULT_CHOICES, LEN);
+
+    let mut group = c.benchmark_group("generate");
+    group.sample_size(10);
+    group.bench_function("25 questions, 4 mistakes", |b| {
+        b.iter(|| attempt.generate_valid_set(&choices).unwrap())
+    });
+    group.finish();
+}
+
+fn reduce(c: &mut Criterion) {
+    let candidates = top_attempt(LEN, MISTAKES)
+        .generate_valid_set(&Choices::uniform(&DEFAULT_CHOICES, LEN))
+        .unwrap();
+    let attempts = other_attempts(LEN);
+
+    let mut group = c.benchmark_group("reduce");
+    group.sample_size(10);
+    group.bench_function("8 attempts", |b| {
+        b.iter_batched(
+            || AnswerKeySet::from(candidates.keys().to_vec()),
+            |set| attempts.iter().fold(set, |set, att| set.reduce(att)),
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+// Generating the top attempt's keys and reducing them against searching with
+// `solve_constrained`, for seeds allowing from about 10^2 to 10^6 keys and
+// with few or many other attempts to constrain the search, to place
+// `GENERATE_LIMIT` in solve.rs.
+fn strategy(c: &mut Criterion) {
+    let mut group = c.benchmark_group("strategy");
+    group.sample_size(10);
+    for (len, mistakes) in [
+        (6, 2),
+        (8, 2),
+        (10, 3),
+        (12, 4),
+        (14, 4),
+        (16, 4),
+        (20, 4),
+        (25, 4),
+    ] {
+        let choices = Choices::uniform(&DEFAULT_CHOICES, len);
+        let seed = top_attempt(len, mistakes);
+        let candidates = seed.estimate_candidates_with(&choices);
+        for others in [1, 8] {
+            let mut attempts = other_attempts(len);
+            attempts.truncate(others);
+            attempts.insert(0, seed.clone());
+            strategies(&mut group, &attempts, &choices, candidates);
+        }
+    }
+    group.finish();
+}
+
+fn strategies(
+    group: &mut BenchmarkGroup<'_, WallTime>,
+    attempts: &[QuizAttempt],
+    choices: &Choices,
+    candidates: u128,
+) {
+    let seed = &attempts[0];
+    let others = attempts.len() - 1;
+    group.bench_with_input(
+        BenchmarkId::new(
+            format!("generate and reduce, {} others", others),
+            candidates,
+        ),
+        attempts,
+        |b, attempts| {
+            b.iter(|| {
+                attempts
+                    .iter()
+                    .fold(seed.generate_valid_set(choices).unwrap(), |set, att| {
+                        set.reduce(att)
+                    })
+            })
+        },
+    );
+    group.bench_with_input(
+        BenchmarkId::new(format!("search, {} others", others), candidates),
+        attempts,
+        |b, attempts| b.iter(|| solve_constrained(attempts, choices)),
+    );
+}
+
+criterion_group!(benches, generate, reduce, strategy);
+criterion_main!(benches);
@@ -0,0 +1,115 @@
+use core::fmt;
+
+use crate::InvalidAnswerChar;
+
+#[derive(PartialOrd, Ord, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Answer {
+    A,
+    B,
+    C,
+    D,
+    E,
+    F,
+    /// True, for true/false questions, where `F` is false.
+    T,
+    X,
+}
+
+impl Answer {
+    /// Every answer except the blank `X`, multiple-choice letters first.
+    pub const fn choices() -> &'static [Answer] {
+        &[
+            Answer::A,
+            Answer::B,
+            Answer::C,
+            Answer::D,
+            Answer::E,
+            Answer::F,
+            Answer::T,
+        ]
+    }
+}
+
+pub const DEFAULT_CHOICES: [Answer; 4] = [Answer::A, Answer::B, Answer::C, Answer::D];
+
+pub const TRUE_FALSE: [Answer; 2] = [Answer::T, Answer::F];
+
+/// Spaces and dashes may separate answers for readability, as in "A B C D".
+pub(crate) fn is_separator(c: char) -> bool {
+    c.is_whitespace() || c == '-'
+}
+
+impl fmt::Display for Answer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Answer::A => write!(f, "A"),
+            Answer::B => write!(f, "B"),
+            Answer::C => write!(f, "C"),
+            Answer::D => write!(f, "D"),
+            Answer::E => write!(f, "E"),
+            Answer::F => write!(f, "F"),
+            Answer::T => write!(f, "T"),
+            Answer::X => write!(f, "X"),
+        }
+    }
+}
+
+impl TryFrom<char> for Answer {
+    type Error = InvalidAnswerChar;
+
+    fn try_from(value: char) -> Result<Answer, InvalidAnswerChar> {
+        match value.to_ascii_uppercase() {
+            'A' => Ok(Answer::A),
+            'B' => Ok(Answer::B),
+            'C' => Ok(Answer::C),
+            'D' => Ok(Answer::D),
+            'E' => Ok(Answer::E),
+            'F' => Ok(Answer::F),
+            'T' => Ok(Answer::T),
+            'X' => Ok(Answer::X),
+            _ => Err(InvalidAnswerChar(value)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Answer, InvalidAnswerChar};
+
+    #[test]
+    fn answer_from_str() {
+        assert_eq!(Answer::try_from('A'), Ok(Answer::A));
+        assert_eq!(Answer::try_from('B'), Ok(Answer::B));
+        assert_eq!(Answer::try_from('C'), Ok(Answer::C));
+        assert_eq!(Answer::try_from('D'), Ok(Answer::D));
+        assert_eq!(Answer::try_from('E'), Ok(Answer::E));
+        assert_eq!(Answer::try_from('F'), Ok(Answer::F));
+        assert_eq!(Answer::try_from('T'), Ok(Answer::T));
+        assert_eq!(Answer::try_from('X'), Ok(Answer::X));
+    }
+
+    #[test]
+    fn answer_try_from_char() {
+        assert_eq!(Answer::try_from('c'), Ok(Answer::C));
+        assert_eq!(Answer::try_from('x'), Ok(Answer::X));
+        assert_eq!(Answer::try_from('t'), Ok(Answer::T));
+        assert_eq!(Answer::T.to_string(), "T");
+    }
+
+    #[test]
+    fn choices_exclude_blank() {
+        assert!(!Answer::choices().contains(&Answer::X));
+        assert_eq!(Answer::choices().len(), 7);
+        assert_eq!(&Answer::choices()[..4], &crate::DEFAULT_CHOICES);
+        for &ans in Answer::choices() {
+            let letter = ans.to_string().chars().next().unwrap();
+            assert_eq!(Answer::try_from(letter), Ok(ans));
+        }
+    }
+
+    #[test]
+    fn invalid_answer() {
+        assert_eq!(Answer::try_from('G'), Err(InvalidAnswerChar('G')));
+    }
+}
@@ -0,0 +1,1641 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, BufWriter, Write};
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+
+use itertools::{Either, Itertools};
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+use crate::answer::is_separator;
+use crate::key::{create_output, keys_differing_at};
+use crate::{
+    Answer, AnswerKey, AnswerKeySet, Choices, InvalidAnswerChar, LengthMismatch, ParseError, Score,
+    ScoringScheme, SolveError, DEFAULT_CHOICES,
+};
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct QuizAttempt {
+    // who made the attempt, for reports only
+    id: Option<String>,
+    answers: Vec<Answer>,
+    score: Score,
+    scheme: ScoringScheme,
+    // number of correct answers implied by `score` under `scheme`
+    correct: usize,
+    // how far the number of correct answers under a key may be from `correct`
+    tolerance: usize,
+}
+
+// scores are always finite
+impl Eq for QuizAttempt {}
+
+impl PartialOrd for QuizAttempt {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for QuizAttempt {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.score.total_cmp(&other.score)
+    }
+}
+
+impl FromStr for QuizAttempt {
+    type Err = ParseError;
+
+    /// Parses answers followed by the score, separated by whitespace or a
+    /// comma, e.g. `ABCDABCD 6` or `ABCDABCD,6`.
+    fn from_str(s: &str) -> Result<QuizAttempt, ParseError> {
+        let (answers, score) = s
+            .trim()
+            .rsplit_once(|c: char| c == ',' || c.is_whitespace())
+            .ok_or_else(|| ParseError::MissingScore(s.trim().to_string()))?;
+        let answers = answers.trim_end_matches(|c: char| c == ',' || c.is_whitespace());
+        QuizAttempt::try_from_list(&[answers, score])
+    }
+}
+
+impl QuizAttempt {
+    pub fn id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    pub fn with_id(mut self, id: impl Into<String>) -> QuizAttempt {
+        self.id = Some(id.into());
+        self
+    }
+
+    pub fn answers(&self) -> &[Answer] {
+        &self.answers
+    }
+
+    pub fn score(&self) -> Score {
+        self.score
+    }
+
+    pub fn scheme(&self) -> &ScoringScheme {
+        &self.scheme
+    }
+
+    pub fn tolerance(&self) -> usize {
+        self.tolerance
+    }
+
+    /// This attempt, also consistent with keys under which it gets up to
+    /// `tolerance` more or fewer answers right than its score says, for
+    /// scores that may have been tallied wrong. A slip then no longer rules
+    /// out the true key, but more keys survive every attempt, and the scores
+    /// alone settle fewer questions.
+    pub fn with_tolerance(mut self, tolerance: usize) -> QuizAttempt {
+        self.tolerance = tolerance;
+        self
+    }
+
+    pub fn as_string(&self) -> String {
+        self.answers.iter().map(|ans| ans.to_string()).collect()
+    }
+
+    pub(crate) fn correct(&self) -> usize {
+        self.correct
+    }
+
+    pub fn try_from_string(string: &str, score: Score) -> Result<QuizAttempt, ParseError> {
+        QuizAttempt::try_from_string_with(string, score, ScoringScheme::default())
+    }
+
+    pub fn try_from_string_with(
+        string: &str,
+        score: Score,
+        scheme: ScoringScheme,
+    ) -> Result<QuizAttempt, ParseError> {
+        let answers = string
+            .to_uppercase()
+            .chars()
+            .filter(|&c| !is_separator(c))
+            .map(Answer::try_from)
+            .collect::<Result<Vec<Answer>, InvalidAnswerChar>>()?;
+        QuizAttempt::new_with(answers, score, scheme)
+    }
+
+    /// An attempt scored one point per correct answer, checking that `score`
+    /// is one it could have got.
+    pub fn new(answers: Vec<Answer>, score: Score) -> Result<QuizAttempt, ParseError> {
+        QuizAttempt::new_with(answers, score, ScoringScheme::default())
+    }
+
+    /// Like [`QuizAttempt::new`] under any scoring scheme, whose weights have
+    /// to match the answers.
+    pub fn new_with(
+        answers: Vec<Answer>,
+        score: Score,
+        scheme: ScoringScheme,
+    ) -> Result<QuizAttempt, ParseError> {
+        if answers.is_empty() {
+            return Err(ParseError::NoAnswers);
+        }
+
+        if scheme.is_weighted() {
+            if scheme.weights.len() != answers.len() {
+                return Err(ParseError::WeightsMismatch {
+                    weights: scheme.weights.len(),
+                    len: answers.len(),
+                });
+            }
+            if let Some(&w) = scheme.weights.iter().find(|&&w| w <= 0) {
+                return Err(ParseError::InvalidWeight(w));
+            }
+        }
+
+        let weight = |blank: bool| -> usize {
+            (0..answers.len())
+                .filter(|&q| (answers[q] == Answer::X) == blank)
+                .map(|q| scheme.weight(q))
+                .sum()
+        };
+        let (answered, blank) = (weight(false), weight(true));
+        let correct = scheme
+            .correct_for(score, answered, blank, answered + blank)
+            .ok_or(ParseError::ImpossibleScore {
+                score,
+                len: answers.len(),
+            })?;
+        if correct > answered {
+            return Err(ParseError::MoreCorrectThanAnswered { score, answered });
+        }
+
+        Ok(QuizAttempt {
+            id: None,
+            answers,
+            score,
+            scheme,
+            correct,
+            tolerance: 0,
+        })
+    }
+
+    /// This attempt with blanks added at the end up to `len` answers, scored
+    /// as blanks. Longer attempts are returned unchanged.
+    pub fn padded_to(&self, len: usize) -> Result<QuizAttempt, ParseError> {
+        let blanks = len.saturating_sub(self.answers.len());
+        let answers = self.as_string() + &"X".repeat(blanks);
+        let padded = QuizAttempt::try_from_string_with(&answers, self.score, self.scheme.clone())?
+            .with_tolerance(self.tolerance);
+        Ok(match &self.id {
+            Some(id) => padded.with_id(id.clone()),
+            None => padded,
+        })
+    }
+
+    pub fn from_string(string: &str, score: Score) -> QuizAttempt {
+        QuizAttempt::try_from_string(string, score).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_from_list(list: &[&str]) -> Result<QuizAttempt, ParseError> {
+        QuizAttempt::try_from_list_with(list, ScoringScheme::default())
+    }
+
+    /// Parses `[answers, score]`, or `[id, answers, score]` with a leading
+    /// identifier such as a student ID.
+    pub fn try_from_list_with(
+        list: &[&str],
+        scheme: ScoringScheme,
+    ) -> Result<QuizAttempt, ParseError> {
+        let (id, answers, score) = match *list {
+            [answers] => return Err(ParseError::MissingScore(answers.trim().to_string())),
+            [answers, score] => (None, answers, score),
+            [id, answers, score] => (Some(id.trim()), answers, score),
+            _ => return Err(ParseError::FieldCount(list.len())),
+        };
+        let score = score
+            .trim()
+            .parse::<Score>()
+            .ok()
+            .filter(|score| score.is_finite())
+            .ok_or_else(|| ParseError::ScoreNotANumber(score.to_string()))?;
+        let attempt = QuizAttempt::try_from_string_with(answers.trim(), score, scheme)?;
+        Ok(match id {
+            Some(id) => attempt.with_id(id),
+            None => attempt,
+        })
+    }
+
+    pub fn from_list(list: &[&str]) -> QuizAttempt {
+        QuizAttempt::try_from_list(list).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Whether `key` would give this attempt its recorded score. An answer is
+    /// correct if `key` accepts it for that question. A blank (`X`) answer
+    /// never scores, even against a key that holds `X` in that position.
+    ///
+    /// Since the score only depends on how many answers are correct, this
+    /// compares that count, weighted if the scheme has weights, rather than
+    /// the points under the scoring scheme. It may be off by up to the
+    /// [`QuizAttempt::with_tolerance`].
+    pub fn check(&self, key: &AnswerKey) -> bool {
+        self.score_against(key).abs_diff(self.correct as i32) as usize <= self.tolerance
+    }
+
+    /// How many answers `key` accepts, or their total weight. Blanks never
+    /// count. Unlike [`QuizAttempt::implied_score`] this ignores the points
+    /// under the scoring scheme.
+    pub fn score_against(&self, key: &AnswerKey) -> i32 {
+        if self.answers.len() != key.answers().len() {
+            panic!("Unmatched lengths!");
+        }
+        self.correct_under(key) as i32
+    }
+
+    // weight of the answers that `key` accepts, blanks never count
+    fn correct_under(&self, key: &AnswerKey) -> usize {
+        self.answers
+            .iter()
+            .enumerate()
+            .filter(|&(q, &x)| x != Answer::X && key.accepts(q, x))
+            .map(|(q, _)| self.scheme.weight(q))
+            .sum()
+    }
+
+    fn blank_weight(&self) -> usize {
+        (0..self.answers.len())
+            .filter(|&q| self.answers[q] == Answer::X)
+            .map(|q| self.scheme.weight(q))
+            .sum()
+    }
+
+    fn total_weight(&self) -> usize {
+        (0..self.answers.len()).map(|q| self.scheme.weight(q)).sum()
+    }
+
+    /// How many questions were not answered correctly, blanks included.
+    /// With question weights this is their total weight instead, as the
+    /// number of questions behind it differs from key to key.
+    pub fn num_mistakes(&self) -> usize {
+        self.total_weight() - self.correct
+    }
+
+    /// The numbers of correct answers, or their weights, that keys consistent
+    /// with this attempt can give it.
+    pub(crate) fn correct_range(&self) -> RangeInclusive<usize> {
+        let answered = self.total_weight() - self.blank_weight();
+        self.correct.saturating_sub(self.tolerance)..=(self.correct + self.tolerance).min(answered)
+    }
+
+    // like `num_mistakes`, for every number of correct answers in range
+    fn mistake_range(&self) -> RangeInclusive<usize> {
+        let (total, correct) = (self.total_weight(), self.correct_range());
+        total.saturating_sub(*correct.end())..=total.saturating_sub(*correct.start())
+    }
+
+    // weights of the answered questions that were missed
+    fn answered_mistakes(&self) -> Result<RangeInclusive<usize>, SolveError> {
+        let answered = self.total_weight() - self.blank_weight();
+        if self.correct > answered {
+            return Err(SolveError::ScoreExceedsLength {
+                score: self.score,
+                len: self.answers.len(),
+            });
+        }
+        let correct = self.correct_range();
+        Ok(answered - correct.end()..=answered - correct.start())
+    }
+
+    // `mistake_sets` for each missed weight in `lefts`
+    fn mistake_sets_in(
+        &self,
+        lefts: RangeInclusive<usize>,
+    ) -> impl Iterator<Item = Vec<usize>> + Send + '_ {
+        lefts.flat_map(move |left| self.mistake_sets(left))
+    }
+
+    // Every set of positions whose weights add up to the missed weight, with
+    // `left` of it among the answered positions. A blank never matches, so
+    // blanks are in every set and only the answered positions are chosen
+    // among. These are plain combinations when every question counts once.
+    fn mistake_sets(&self, left: usize) -> impl Iterator<Item = Vec<usize>> + Send {
+        let (blanks, answered): (Vec<usize>, Vec<usize>) =
+            (0..self.answers.len()).partition(|&q| self.answers[q] == Answer::X);
+        let with_blanks = move |chosen: Vec<usize>| {
+            let mut set = blanks.clone();
+            set.extend(chosen);
+            set.sort_unstable();
+            set
+        };
+
+        if !self.scheme.is_weighted() {
+            return Either::Left(answered.into_iter().combinations(left).map(with_blanks));
+        }
+
+        fn extend(
+            weights: &[usize],
+            suffix: &[usize],
+            q: usize,
+            left: usize,
+            current: &mut Vec<usize>,
+            out: &mut Vec<Vec<usize>>,
+        ) {
+            if left == 0 {
+                out.push(current.clone());
+                return;
+            }
+            if q == weights.len() || suffix[q] < left {
+                return;
+            }
+            if weights[q] <= left {
+                current.push(q);
+                extend(weights, suffix, q + 1, left - weights[q], current, out);
+                current.pop();
+            }
+            extend(weights, suffix, q + 1, left, current, out);
+        }
+
+        let weights: Vec<usize> = answered.iter().map(|&q| self.scheme.weight(q)).collect();
+        let mut suffix = vec![0; weights.len() + 1];
+        for i in (0..weights.len()).rev() {
+            suffix[i] = suffix[i + 1] + weights[i];
+        }
+        let mut sets = vec![];
+        extend(&weights, &suffix, 0, left, &mut vec![], &mut sets);
+        Either::Right(
+            sets.into_iter()
+                .map(move |set| with_blanks(set.iter().map(|&i| answered[i]).collect())),
+        )
+    }
+
+    /// The score this attempt would have been given under `key`.
+    pub fn implied_score(&self, key: &AnswerKey) -> Score {
+        if self.answers.len() != key.answers().len() {
+            panic!("Unmatched lengths!");
+        }
+        let blank = self.blank_weight();
+        let correct = self.correct_under(key);
+
+        self.scheme
+            .total(correct, self.total_weight() - correct - blank, blank)
+    }
+
+    /// Lazily yield every key allowed by `choices` that gives this attempt its
+    /// recorded score.
+    pub fn valid_keys_iter<'a>(
+        &'a self,
+        choices: &'a Choices,
+    ) -> Result<impl Iterator<Item = AnswerKey> + 'a, SolveError> {
+        if self.answers.len() != choices.len() {
+            panic!("Unmatched lengths!");
+        }
+        let lefts = self.answered_mistakes()?;
+        Ok(self
+            .mistake_sets_in(lefts)
+            .flat_map(move |possible_mistakes| {
+                keys_differing_at(&self.answers, choices, possible_mistakes)
+            })
+            .filter(|key| choices.allows(key.answers())))
+    }
+
+    /// Write each key from [`QuizAttempt::valid_keys_iter`] that is also
+    /// consistent with `attempts` to `filename` as soon as it is found, one per
+    /// line in generation order, returning how many were written. A
+    /// [`SolveError`] is returned as an [`io::ErrorKind::InvalidInput`] error.
+    pub fn generate_and_reduce_to_file(
+        &self,
+        choices: &Choices,
+        attempts: &[QuizAttempt],
+        filename: &str,
+    ) -> io::Result<u64> {
+        self.write_valid_keys(choices, attempts, BufWriter::new(create_output(filename)?))
+    }
+
+    /// Like [`QuizAttempt::generate_and_reduce_to_file`] for any writer.
+    pub fn write_valid_keys<W: Write>(
+        &self,
+        choices: &Choices,
+        attempts: &[QuizAttempt],
+        mut w: W,
+    ) -> io::Result<u64> {
+        let keys = self
+            .valid_keys_iter(choices)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        let mut written = 0;
+        for key in keys {
+            if key.consistent_with(attempts) {
+                writeln!(w, "{}", key.as_string())?;
+                written += 1;
+            }
+        }
+        w.flush()?;
+        Ok(written)
+    }
+
+    /// How many sets of mistake positions candidate generation goes through,
+    /// each holding all the blanks.
+    pub fn mistake_position_sets(&self) -> u64 {
+        let positions = self
+            .answers
+            .iter()
+            .enumerate()
+            .map(|(q, &ans)| (self.scheme.weight(q), 1, ans != Answer::X));
+        count_keys(positions, self.mistake_range())
+            .try_into()
+            .unwrap_or(u64::MAX)
+    }
+
+    /// How many keys [`QuizAttempt::generate_valid_set`] yields with a
+    /// uniform alphabet of `num_choices` letters, saturating at `u128::MAX`.
+    /// Blanks always count as mistakes, and there are no keys without choices.
+    pub fn estimate_candidates(&self, num_choices: usize) -> u128 {
+        if num_choices == 0 {
+            return 0;
+        }
+        let positions = self.answers.iter().enumerate().map(|(q, &ans)| {
+            let blank = ans == Answer::X;
+            let alternatives = num_choices - !blank as usize;
+            (self.scheme.weight(q), alternatives as u128, !blank)
+        });
+        count_keys(positions, self.mistake_range())
+    }
+
+    /// Like [`QuizAttempt::estimate_candidates`] for any `choices`. Questions
+    /// whose attempted answer is not a choice always count as mistakes.
+    pub fn estimate_candidates_with(&self, choices: &Choices) -> u128 {
+        if self.answers.len() != choices.len() {
+            panic!("Unmatched lengths!");
+        }
+        let positions = self.answers.iter().enumerate().map(|(q, ans)| {
+            let allowed = choices.at(q).contains(ans);
+            let alternatives = choices.at(q).len() - allowed as usize;
+            (self.scheme.weight(q), alternatives as u128, allowed)
+        });
+        count_keys(positions, self.mistake_range())
+    }
+
+    /// The number of answers every attempt has, 0 when there are none.
+    pub fn validate_uniform_length(attempts: &[QuizAttempt]) -> Result<usize, LengthMismatch> {
+        let mut lengths: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+        for (i, att) in attempts.iter().enumerate() {
+            lengths.entry(att.answers.len()).or_default().push(i);
+        }
+
+        match lengths.len() {
+            0 => Ok(0),
+            1 => Ok(attempts[0].answers.len()),
+            _ => {
+                // the most common length, the first attempt's on ties
+                let first = attempts[0].answers.len();
+                let expected = lengths.iter().fold(first, |best, (&len, atts)| {
+                    if atts.len() > lengths[&best].len() {
+                        len
+                    } else {
+                        best
+                    }
+                });
+                Err(LengthMismatch {
+                    expected,
+                    lengths: lengths.into_iter().collect(),
+                })
+            }
+        }
+    }
+
+    /// Every answer given in any of `attempts`, blanks included.
+    pub fn observed_alphabet(attempts: &[QuizAttempt]) -> BTreeSet<Answer> {
+        attempts
+            .iter()
+            .flat_map(|att| att.answers.iter().copied())
+            .collect()
+    }
+
+    /// For each question, how many attempts gave each answer, blanks included.
+    /// A map rather than a fixed `[usize; 5]` over A to D and X, as answers
+    /// can also be E, F or T, and only the answers given appear in it.
+    pub fn answer_histogram(
+        attempts: &[QuizAttempt],
+    ) -> Result<Vec<BTreeMap<Answer, usize>>, LengthMismatch> {
+        let len = QuizAttempt::validate_uniform_length(attempts)?;
+        let mut counts = vec![BTreeMap::new(); len];
+        for att in attempts {
+            for (q, &ans) in att.answers.iter().enumerate() {
+                *counts[q].entry(ans).or_insert(0) += 1;
+            }
+        }
+        Ok(counts)
+    }
+
+    /// The answer given most often to each question, the earlier letter on
+    /// ties and never the blank, `None` where every attempt left it blank.
+    pub fn plurality_answers(
+        attempts: &[QuizAttempt],
+    ) -> Result<Vec<Option<Answer>>, LengthMismatch> {
+        let histogram = QuizAttempt::answer_histogram(attempts)?;
+        Ok(histogram
+            .iter()
+            .map(|counts| {
+                counts
+                    .iter()
+                    .filter(|(&ans, _)| ans != Answer::X)
+                    .fold(
+                        None,
+                        |best: Option<(Answer, usize)>, (&ans, &n)| match best {
+                            Some((_, best_n)) if best_n >= n => best,
+                            _ => Some((ans, n)),
+                        },
+                    )
+                    .map(|(ans, _)| ans)
+            })
+            .collect())
+    }
+
+    /// For each question, the correlation between giving its
+    /// [`QuizAttempt::plurality_answers`] answer and the overall score, from
+    /// -1 to 1. Questions high scorers tend to answer that way and low
+    /// scorers not tell them apart best. It is 0 where everyone or no one
+    /// gave that answer, or all scores are the same.
+    pub fn question_discrimination(attempts: &[QuizAttempt]) -> Result<Vec<f64>, LengthMismatch> {
+        let plurality = QuizAttempt::plurality_answers(attempts)?;
+        let n = attempts.len() as f64;
+        let mean_score = attempts.iter().map(|att| att.score).sum::<f64>() / n;
+
+        Ok(plurality
+            .iter()
+            .enumerate()
+            .map(|(q, &common)| {
+                let gave: Vec<f64> = attempts
+                    .iter()
+                    .map(|att| (Some(att.answers[q]) == common) as u8 as f64)
+                    .collect();
+                let mean_gave = gave.iter().sum::<f64>() / n;
+
+                let (mut cov, mut var_gave, mut var_score) = (0.0, 0.0, 0.0);
+                for (g, att) in gave.iter().zip(attempts) {
+                    cov += (g - mean_gave) * (att.score - mean_score);
+                    var_gave += (g - mean_gave).powi(2);
+                    var_score += (att.score - mean_score).powi(2);
+                }
+                if var_gave == 0.0 || var_score == 0.0 {
+                    0.0
+                } else {
+                    cov / (var_gave * var_score).sqrt()
+                }
+            })
+            .collect())
+    }
+
+    /// For each question, the answer that more than `threshold` of the upper
+    /// half of `attempts` agree on, or `None`. Each attempt scoring at least
+    /// the median counts in proportion to its fraction correct. This is only
+    /// a guess: even a strong majority can be wrong, and the true key with it.
+    pub fn agreed_answers(
+        attempts: &[QuizAttempt],
+        threshold: f64,
+    ) -> Result<Vec<Option<Answer>>, LengthMismatch> {
+        let len = QuizAttempt::validate_uniform_length(attempts)?;
+        let mut correct: Vec<usize> = attempts.iter().map(|att| att.correct).collect();
+        correct.sort_unstable();
+        let median = correct.get(correct.len() / 2).copied().unwrap_or(0);
+        let top: Vec<(&QuizAttempt, f64)> = attempts
+            .iter()
+            .filter(|att| att.correct >= median)
+            .map(|att| (att, att.correct as f64 / att.total_weight() as f64))
+            .collect();
+        let total: f64 = top.iter().map(|(_, w)| w).sum();
+
+        Ok((0..len)
+            .map(|q| {
+                if total == 0.0 {
+                    return None;
+                }
+                let mut agreement: BTreeMap<Answer, f64> = BTreeMap::new();
+                for (att, w) in &top {
+                    *agreement.entry(att.answers[q]).or_insert(0.0) += w;
+                }
+                agreement
+                    .into_iter()
+                    .find(|&(ans, w)| ans != Answer::X && w / total > threshold)
+                    .map(|(ans, _)| ans)
+            })
+            .collect())
+    }
+
+    /// The attempt allowing the fewest candidate keys with the default
+    /// choices, the earliest one on ties. Panics if `attempts` is empty.
+    pub fn best_seed(attempts: &[QuizAttempt]) -> &QuizAttempt {
+        attempts
+            .iter()
+            .min_by_key(|att| att.estimate_candidates(DEFAULT_CHOICES.len()))
+            .expect("No attempts to seed from!")
+    }
+
+    /// Like [`QuizAttempt::best_seed`] for any `choices`.
+    pub fn best_seed_with<'a>(attempts: &'a [QuizAttempt], choices: &Choices) -> &'a QuizAttempt {
+        attempts
+            .iter()
+            .min_by_key(|att| att.estimate_candidates_with(choices))
+            .expect("No attempts to seed from!")
+    }
+
+    /// Every key allowed by `choices` that gives this attempt its recorded
+    /// score, or [`SolveError::ScoreExceedsLength`] if the score is more
+    /// than the answers could get.
+    pub fn generate_valid_set(&self, choices: &Choices) -> Result<AnswerKeySet, SolveError> {
+        self.generate_valid_set_with_progress(choices, || ())
+    }
+
+    /// Like [`QuizAttempt::generate_valid_set`], calling `on_step` once for
+    /// each of the [`QuizAttempt::mistake_position_sets`] it goes through.
+    pub fn generate_valid_set_with_progress<F: Fn() + Sync>(
+        &self,
+        choices: &Choices,
+        on_step: F,
+    ) -> Result<AnswerKeySet, SolveError> {
+        if self.answers.len() != choices.len() {
+            panic!("Unmatched lengths!");
+        }
+        let lefts = self.answered_mistakes()?;
+        // full marks leave nothing to search, the answers are the key
+        if self.num_mistakes() == 0 && self.tolerance == 0 {
+            on_step();
+            return Ok(if choices.allows(&self.answers) {
+                AnswerKeySet::from(vec![AnswerKey::from(self.answers.clone())])
+            } else {
+                AnswerKeySet::from(vec![])
+            });
+        }
+        let possible_mistakes = self.mistake_sets_in(lefts);
+
+        #[cfg(not(feature = "rayon"))]
+        let mut small_set = possible_mistakes
+            .flat_map(|possible_mistakes| {
+                on_step();
+                keys_differing_at(&self.answers, choices, possible_mistakes)
+            })
+            .filter(|key| choices.allows(key.answers()))
+            .collect::<Vec<AnswerKey>>();
+        #[cfg(feature = "rayon")]
+        let mut small_set = possible_mistakes
+            .par_bridge()
+            .flat_map_iter(|possible_mistakes| {
+                on_step();
+                keys_differing_at(&self.answers, choices, possible_mistakes)
+            })
+            .filter(|key| choices.allows(key.answers()))
+            .collect::<Vec<AnswerKey>>();
+
+        // keys are unique by construction, sorting only makes the order reproducible
+        #[cfg(not(feature = "rayon"))]
+        small_set.sort_unstable();
+        #[cfg(feature = "rayon")]
+        small_set.par_sort_unstable();
+
+        Ok(AnswerKeySet::from(small_set))
+    }
+
+    /// Up to `n` random keys allowed by `choices` that give this attempt its
+    /// recorded score, for when the full set is too large to generate. The
+    /// same `seed` gives the same sample.
+    #[cfg(feature = "rand")]
+    pub fn sample_valid_keys(
+        &self,
+        choices: &Choices,
+        n: usize,
+        seed: u64,
+    ) -> Result<AnswerKeySet, SolveError> {
+        use rand::rngs::StdRng;
+        use rand::seq::SliceRandom;
+        use rand::{Rng, SeedableRng};
+
+        if self.answers.len() != choices.len() {
+            panic!("Unmatched lengths!");
+        }
+        let lefts = self.answered_mistakes()?;
+        let mut rng = StdRng::seed_from_u64(seed);
+        // as in `mistake_sets`, blanks are always mistakes
+        let (blanks, mut answered): (Vec<usize>, Vec<usize>) =
+            (0..self.answers.len()).partition(|&q| self.answers[q] == Answer::X);
+
+        let mut keys: Vec<AnswerKey> = (0..n)
+            .filter_map(|_| {
+                // take answered positions in random order until the missed
+                // weight, anywhere within the tolerance, is used up
+                answered.shuffle(&mut rng);
+                let mut left = rng.gen_range(lefts.clone());
+                let mut mistakes = blanks.clone();
+                for &q in &answered {
+                    if left == 0 {
+                        break;
+                    }
+                    if self.scheme.weight(q) <= left {
+                        left -= self.scheme.weight(q);
+                        mistakes.push(q);
+                    }
+                }
+                let mut key = self.answers.clone();
+                for q in mistakes {
+                    let alternatives: Vec<Answer> = choices
+                        .at(q)
+                        .iter()
+                        .filter(|&&c| c != self.answers[q])
+                        .copied()
+                        .collect();
+                    key[q] = *alternatives.choose(&mut rng)?;
+                }
+                Some(AnswerKey::from(key))
+            })
+            .filter(|key| choices.allows(key.answers()) && self.check(key))
+            .collect();
+
+        keys.sort_unstable();
+        keys.dedup();
+        Ok(AnswerKeySet::from(keys))
+    }
+
+    /// Like [`QuizAttempt::generate_valid_set`], but also yields keys where up
+    /// to `max_multi` questions accept more than one answer.
+    pub fn generate_multi_answer_set(
+        &self,
+        choices: &Choices,
+        max_multi: usize,
+    ) -> Result<AnswerKeySet, SolveError> {
+        let mut keys = vec![];
+        for key in self.valid_keys_iter(choices)? {
+            // A right answer can be joined by any other choice. A wrong one
+            // only by later letters, so that it stays the first accepted
+            // answer and no key is generated twice.
+            let extras: Vec<Vec<Answer>> = (0..self.answers.len())
+                .map(|q| {
+                    let (attempted, first) = (self.answers[q], key.answers()[q]);
+                    choices
+                        .at(q)
+                        .iter()
+                        .filter(|&&c| c != attempted && c != first)
+                        .filter(|&&c| first == attempted || c > first)
+                        .copied()
+                        .collect()
+                })
+                .collect();
+            let widenable: Vec<usize> = (0..self.answers.len())
+                .filter(|&q| !extras[q].is_empty())
+                .collect();
+
+            for n in 1..=max_multi.min(widenable.len()) {
+                for positions in widenable.iter().combinations(n) {
+                    let subsets: Vec<Vec<Vec<Answer>>> = positions
+                        .iter()
+                        .map(|&&q| {
+                            extras[q]
+                                .iter()
+                                .copied()
+                                .powerset()
+                                .filter(|set| !set.is_empty())
+                                .collect()
+                        })
+                        .collect();
+                    for added in subsets.iter().multi_cartesian_product() {
+                        let mut accepted: Vec<Vec<Answer>> =
+                            key.answers().iter().map(|&ans| vec![ans]).collect();
+                        for (&&q, extra) in positions.iter().zip(added) {
+                            accepted[q].extend(extra);
+                        }
+                        keys.push(AnswerKey::with_alternatives(accepted));
+                    }
+                }
+            }
+            keys.push(key);
+        }
+
+        keys.sort_unstable();
+        Ok(AnswerKeySet::from(keys))
+    }
+}
+
+// Each position is given as (weight, ways to get it wrong, whether it can be
+// right). Counts the keys over every set of wrong positions whose weights add
+// up to one of `targets`, saturating. With unit weights that are all allowed
+// to be right this is C(n, target) * ways^target for each target.
+fn count_keys(
+    positions: impl Iterator<Item = (usize, u128, bool)>,
+    targets: RangeInclusive<usize>,
+) -> u128 {
+    let target = *targets.end();
+    let mut ways = vec![0u128; target + 1];
+    ways[0] = 1;
+    for (weight, wrong, can_be_right) in positions {
+        let mut next = if can_be_right {
+            ways.clone()
+        } else {
+            vec![0; target + 1]
+        };
+        for sum in weight..=target {
+            next[sum] = next[sum].saturating_add(ways[sum - weight].saturating_mul(wrong));
+        }
+        ways = next;
+    }
+    ways[targets]
+        .iter()
+        .fold(0u128, |sum, &n| sum.saturating_add(n))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        Answer, AnswerKey, AnswerKeySet, Choices, LengthMismatch, ParseError, QuizAttempt,
+        ScoringScheme, SolveError, DEFAULT_CHOICES, TRUE_FALSE,
+    };
+    use itertools::Itertools;
+    use std::collections::HashSet;
+
+    fn attempt(answers: Vec<Answer>, correct: usize) -> QuizAttempt {
+        QuizAttempt {
+            id: None,
+            answers,
+            score: correct as f64,
+            scheme: ScoringScheme::default(),
+            correct,
+            tolerance: 0,
+        }
+    }
+
+    #[test]
+    fn try_from_string_errors() {
+        assert_eq!(
+            QuizAttempt::try_from_string("ABGX", 2.0),
+            Err(ParseError::InvalidLetter('G'))
+        );
+        assert_eq!(
+            QuizAttempt::try_from_string("AB", 3.0),
+            Err(ParseError::ImpossibleScore { score: 3.0, len: 2 })
+        );
+        assert_eq!(
+            QuizAttempt::try_from_string("ABXD", 4.0),
+            Err(ParseError::MoreCorrectThanAnswered {
+                score: 4.0,
+                answered: 3
+            })
+        );
+        assert_eq!(
+            QuizAttempt::try_from_list(&["AB", "two"]),
+            Err(ParseError::ScoreNotANumber("two".to_string()))
+        );
+        assert_eq!(
+            QuizAttempt::try_from_list(&["AB"]),
+            Err(ParseError::MissingScore("AB".to_string()))
+        );
+        assert_eq!(
+            QuizAttempt::try_from_list(&["1", "AB", "2", "3"]),
+            Err(ParseError::FieldCount(4))
+        );
+        assert_eq!(
+            QuizAttempt::try_from_list(&[" ", "0"]),
+            Err(ParseError::NoAnswers)
+        );
+        assert_eq!(
+            QuizAttempt::try_from_string(" a b-C\td ", 2.0),
+            QuizAttempt::try_from_string("ABCD", 2.0)
+        );
+        assert_eq!(
+            QuizAttempt::try_from_string("A_B", 1.0),
+            Err(ParseError::InvalidLetter('_'))
+        );
+        assert_eq!(
+            QuizAttempt::try_from_string("ab", 1.0),
+            Ok(attempt(vec![Answer::A, Answer::B], 1))
+        );
+    }
+
+    #[test]
+    fn pads_with_blanks() {
+        let att = QuizAttempt::from_string("AB", 1.0).with_id("s1");
+        let padded = att.padded_to(4).unwrap();
+        assert_eq!(padded.as_string(), "ABXX");
+        assert_eq!(padded.id(), Some("s1"));
+        assert_eq!(padded.num_mistakes(), 3);
+        assert_eq!(padded.padded_to(2).unwrap(), padded);
+
+        // padded positions score as blanks
+        let scheme = ScoringScheme {
+            blank: 0.5,
+            ..ScoringScheme::default()
+        };
+        let att = QuizAttempt::try_from_string_with("AB", 1.0, scheme).unwrap();
+        assert_eq!(att.padded_to(4).unwrap().num_mistakes(), 4);
+    }
+
+    #[test]
+    fn new_validates_score() {
+        assert_eq!(
+            QuizAttempt::new(vec![Answer::A, Answer::X], 1.0),
+            QuizAttempt::try_from_string("AX", 1.0)
+        );
+        assert_eq!(
+            QuizAttempt::new(vec![Answer::A, Answer::X], 2.0),
+            Err(ParseError::MoreCorrectThanAnswered {
+                score: 2.0,
+                answered: 1
+            })
+        );
+        assert_eq!(
+            QuizAttempt::new(vec![Answer::A], 0.5),
+            Err(ParseError::ImpossibleScore { score: 0.5, len: 1 })
+        );
+        assert_eq!(QuizAttempt::new(vec![], 0.0), Err(ParseError::NoAnswers));
+    }
+
+    #[test]
+    fn parses_single_field_attempts() {
+        let expected = QuizAttempt::from_string("ABCD", 3.0);
+        assert_eq!("ABCD 3".parse(), Ok(expected.clone()));
+        assert_eq!("ABCD,3".parse(), Ok(expected.clone()));
+        assert_eq!(" abcd\t, 3.0 ".parse(), Ok(expected));
+        assert_eq!(
+            "ABCD".parse::<QuizAttempt>(),
+            Err(ParseError::MissingScore("ABCD".to_string()))
+        );
+        assert_eq!(
+            "ABCD three".parse::<QuizAttempt>(),
+            Err(ParseError::ScoreNotANumber("three".to_string()))
+        );
+    }
+
+    #[test]
+    fn leading_id_column() {
+        let att = QuizAttempt::try_from_list(&["s042", "ABCD", "3"]).unwrap();
+        assert_eq!(att.id(), Some("s042"));
+        assert_eq!(att.as_string(), "ABCD");
+        assert_eq!(att.score(), 3.0);
+        assert_eq!(QuizAttempt::from_list(&["ABCD", "3"]).id(), None);
+    }
+
+    #[test]
+    fn crosscheck_works_for_valid() {
+        let key = AnswerKey::from(vec![Answer::A, Answer::B]);
+        let att = attempt(vec![Answer::A, Answer::A], 1);
+        assert!(att.check(&key))
+    }
+
+    #[test]
+    fn crosscheck_works_for_invalid() {
+        let key = AnswerKey::from(vec![Answer::A, Answer::B]);
+        let att = attempt(vec![Answer::A, Answer::A], 2);
+        att.check(&key);
+    }
+
+    #[test]
+    fn crosscheck_ignores_blanks() {
+        let att = QuizAttempt::from_string("AXCX", 2.0);
+
+        assert!(att.check(&AnswerKey::from(vec![
+            Answer::A,
+            Answer::B,
+            Answer::C,
+            Answer::D
+        ])));
+        assert!(att.check(&AnswerKey::from(vec![
+            Answer::A,
+            Answer::X,
+            Answer::C,
+            Answer::X
+        ])));
+        assert_eq!(
+            QuizAttempt::try_from_string("AXCX", 3.0),
+            Err(ParseError::MoreCorrectThanAnswered {
+                score: 3.0,
+                answered: 2
+            })
+        );
+    }
+
+    #[test]
+    fn score_against_counts_matches() {
+        let key = AnswerKey::from(vec![Answer::A, Answer::B, Answer::C, Answer::D]);
+
+        assert_eq!(QuizAttempt::from_string("ABCA", 1.0).score_against(&key), 3);
+        assert_eq!(QuizAttempt::from_string("AXCX", 0.0).score_against(&key), 2);
+    }
+
+    #[test]
+    fn implied_score_under_scheme() {
+        let key = AnswerKey::from(vec![Answer::A, Answer::B, Answer::C, Answer::D]);
+        let scheme = ScoringScheme {
+            correct: 1.0,
+            wrong: -0.5,
+            blank: 0.0,
+            weights: vec![],
+        };
+
+        assert_eq!(
+            QuizAttempt::from_string("ABXX", 2.0).implied_score(&key),
+            2.0
+        );
+        assert_eq!(
+            QuizAttempt::try_from_string_with("ACXD", 1.5, scheme)
+                .unwrap()
+                .implied_score(&key),
+            1.5
+        );
+    }
+
+    #[test]
+    fn counts_mistakes() {
+        assert_eq!(QuizAttempt::from_string("ABXX", 1.0).num_mistakes(), 3);
+        let penalty = ScoringScheme {
+            correct: 1.0,
+            wrong: -0.5,
+            blank: 0.0,
+            weights: vec![],
+        };
+        let att = QuizAttempt::try_from_string_with("ACXD", 1.5, penalty).unwrap();
+        assert_eq!(att.num_mistakes(), 2);
+        let weighted = ScoringScheme {
+            weights: vec![1, 2, 3],
+            ..ScoringScheme::default()
+        };
+        let att = QuizAttempt::try_from_string_with("ABC", 4.0, weighted).unwrap();
+        assert_eq!(att.num_mistakes(), 2);
+    }
+
+    #[test]
+    #[should_panic]
+    fn crosscheck_works_for_invalid_length() {
+        let key = AnswerKey::from(vec![Answer::A, Answer::B]);
+        let att = attempt(vec![Answer::A], 2);
+        assert!(!att.check(&key))
+    }
+
+    #[test]
+    fn permutations_checking() {
+        let att = attempt(vec![Answer::A, Answer::B], 1);
+        let mut valid_keys: Vec<AnswerKey> = vec![];
+
+        for key in [Answer::A, Answer::B, Answer::C, Answer::D]
+            .iter()
+            .permutations(2)
+        {
+            let akey = AnswerKey::from(key.into_iter().copied().collect::<Vec<Answer>>());
+            if att.check(&akey) {
+                valid_keys.push(akey)
+            }
+        }
+
+        assert_eq!(valid_keys.len(), 4);
+
+        let att2 = attempt(vec![Answer::A, Answer::B], 2);
+        let mut valid_keys2: Vec<AnswerKey> = vec![];
+        for key2 in [Answer::A, Answer::B, Answer::C, Answer::D]
+            .iter()
+            .permutations(2)
+        {
+            let akey = AnswerKey::from(key2.into_iter().copied().collect::<Vec<Answer>>());
+            if att2.check(&akey) {
+                valid_keys2.push(akey)
+            }
+        }
+        assert_eq!(valid_keys2.len(), 1);
+    }
+
+    #[test]
+    fn generate_respects_choices() {
+        let att = QuizAttempt::from_string("AB", 1.0);
+        let four = att
+            .generate_valid_set(&Choices::uniform(&DEFAULT_CHOICES, 2))
+            .unwrap();
+        let five = att
+            .generate_valid_set(&Choices::uniform(
+                &[Answer::A, Answer::B, Answer::C, Answer::D, Answer::E],
+                2,
+            ))
+            .unwrap();
+
+        assert!(four
+            .keys()
+            .iter()
+            .all(|k| !k.answers().contains(&Answer::E)));
+        assert!(five.keys().iter().any(|k| k.answers().contains(&Answer::E)));
+        assert!(five.keys().len() > four.keys().len());
+    }
+
+    #[test]
+    fn valid_keys_match_seed_score() {
+        let att = QuizAttempt::from_string("AB", 1.0);
+        let keys: Vec<String> = att
+            .valid_keys_iter(&Choices::uniform(&DEFAULT_CHOICES, 2))
+            .unwrap()
+            .map(|k| k.as_string())
+            .sorted()
+            .collect();
+
+        assert_eq!(keys, vec!["AA", "AC", "AD", "BB", "CB", "DB"]);
+        assert_eq!(
+            att.generate_valid_set(&Choices::uniform(&DEFAULT_CHOICES, 2))
+                .unwrap()
+                .keys()
+                .len(),
+            6
+        );
+
+        let att = QuizAttempt::from_string("ABXD", 2.0);
+        assert!(att
+            .valid_keys_iter(&Choices::uniform(&DEFAULT_CHOICES, 4))
+            .unwrap()
+            .all(|k| att.check(&k) && k.answers()[2] != Answer::X));
+    }
+
+    #[test]
+    fn streams_reduced_keys() {
+        let seed = QuizAttempt::from_string("ABCD", 3.0);
+        let attempts = vec![
+            QuizAttempt::from_string("ABCA", 2.0),
+            QuizAttempt::from_string("DBCD", 2.0),
+        ];
+        let choices = Choices::uniform(&DEFAULT_CHOICES, 4);
+        let mut out = vec![];
+        let written = seed
+            .write_valid_keys(&choices, &attempts, &mut out)
+            .unwrap();
+
+        let streamed: AnswerKeySet = String::from_utf8(out).unwrap().parse().unwrap();
+        let reduced = attempts
+            .iter()
+            .fold(seed.generate_valid_set(&choices).unwrap(), |set, att| {
+                set.reduce(att)
+            });
+        assert_eq!(written, 6);
+        assert_eq!(streamed.sorted().keys(), reduced.keys());
+    }
+
+    #[test]
+    fn full_marks_give_the_key() {
+        let att = QuizAttempt::from_string("ABCD", 4.0);
+        let choices = Choices::uniform(&DEFAULT_CHOICES, 4);
+
+        assert_eq!(
+            att.generate_valid_set(&choices).unwrap().to_string(),
+            "ABCD"
+        );
+        assert!(att
+            .generate_valid_set(&Choices::uniform(&[Answer::A, Answer::B], 4))
+            .unwrap()
+            .is_empty());
+        assert_eq!(att.mistake_position_sets(), 1);
+    }
+
+    #[test]
+    fn tolerance_allows_miscounted_scores() {
+        let att = QuizAttempt::from_string("ABXD", 2.0).with_tolerance(1);
+        let choices = Choices::uniform(&DEFAULT_CHOICES, 4);
+        let keys = att.generate_valid_set(&choices).unwrap();
+
+        // 1, 2 or 3 of the answered questions right
+        assert_eq!(keys.len(), 4 * (3 * 9 + 3 * 3 + 1));
+        assert_eq!(att.estimate_candidates(4), keys.len() as u128);
+        assert_eq!(att.estimate_candidates_with(&choices), keys.len() as u128);
+        assert_eq!(att.mistake_position_sets(), 3 + 3 + 1);
+        assert!(keys.iter().all(|key| att.check(key)));
+        assert!(att.check(&"ABCD".parse().unwrap()));
+        assert!(!att.check(&"BACA".parse().unwrap()));
+        assert!(!att.with_tolerance(0).check(&"ABCD".parse().unwrap()));
+    }
+
+    #[test]
+    fn too_high_a_score_is_an_error() {
+        // only reachable by going around the constructors
+        let att = QuizAttempt {
+            correct: 4,
+            ..QuizAttempt::from_string("ABXD", 2.0)
+        };
+        assert_eq!(
+            att.generate_valid_set(&Choices::uniform(&DEFAULT_CHOICES, 4))
+                .err(),
+            Some(SolveError::ScoreExceedsLength { score: 2.0, len: 4 })
+        );
+    }
+
+    #[test]
+    fn blanks_are_always_mistakes() {
+        let att = QuizAttempt::from_string("AXXD", 1.0);
+        let choices = Choices::uniform(&DEFAULT_CHOICES, 4);
+
+        // only Q1 or Q4 is chosen as the other mistake
+        assert_eq!(att.mistake_position_sets(), 2);
+        let keys = att.generate_valid_set(&choices).unwrap();
+        assert_eq!(keys.len(), 2 * 3 * 4 * 4);
+        assert_eq!(keys.len() as u128, att.estimate_candidates(4));
+        assert!(keys.iter().all(|key| att.check(key)));
+
+        let scheme = ScoringScheme {
+            weights: vec![1, 2, 1],
+            ..ScoringScheme::default()
+        };
+        let att = QuizAttempt::try_from_string_with("XBC", 2.0, scheme).unwrap();
+        assert_eq!(att.mistake_position_sets(), 1);
+        assert_eq!(
+            att.generate_valid_set(&Choices::uniform(&DEFAULT_CHOICES, 3))
+                .unwrap()
+                .len(),
+            4 * 3
+        );
+    }
+
+    #[test]
+    fn progress_steps_through_every_position_set() {
+        let att = QuizAttempt::from_string("ABCDA", 3.0);
+        let steps = std::sync::atomic::AtomicU64::new(0);
+
+        att.generate_valid_set_with_progress(&Choices::uniform(&DEFAULT_CHOICES, 5), || {
+            steps.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        })
+        .unwrap();
+
+        assert_eq!(att.mistake_position_sets(), 10);
+        assert_eq!(steps.into_inner(), 10);
+    }
+
+    #[test]
+    fn generated_keys_are_unique() {
+        let att = QuizAttempt::from_string("ABCDXAB", 3.0);
+        let keys = att
+            .generate_valid_set(&Choices::uniform(
+                &[Answer::A, Answer::B, Answer::C, Answer::A, Answer::D],
+                7,
+            ))
+            .unwrap();
+        let unique: HashSet<&AnswerKey> = keys.keys().iter().collect();
+
+        assert_eq!(unique.len(), keys.keys().len());
+        // the blank is always a mistake, plus 3 of the other 6 positions
+        assert_eq!(keys.keys().len(), 20 * 4 * 3 * 3 * 3);
+    }
+
+    #[test]
+    fn estimate_matches_generation() {
+        for (answers, score) in [("ABCDA", 3.0), ("ABXDX", 1.0), ("AB", 2.0)] {
+            let att = QuizAttempt::from_string(answers, score);
+            let len = answers.len();
+            assert_eq!(
+                att.estimate_candidates(4),
+                att.generate_valid_set(&Choices::uniform(&DEFAULT_CHOICES, len))
+                    .unwrap()
+                    .keys()
+                    .len() as u128
+            );
+        }
+        assert_eq!(
+            QuizAttempt::from_string(&"A".repeat(200), 0.0).estimate_candidates(4),
+            u128::MAX
+        );
+        for (answers, score) in [("ABCD", 4.0), ("ABXD", 1.0)] {
+            assert_eq!(
+                QuizAttempt::from_string(answers, score).estimate_candidates(0),
+                0
+            );
+        }
+    }
+
+    #[test]
+    fn validates_uniform_length() {
+        let attempts = vec![
+            QuizAttempt::from_string("ABCD", 3.0),
+            QuizAttempt::from_string("ABC", 2.0),
+            QuizAttempt::from_string("ABCA", 2.0),
+            QuizAttempt::from_string("AB", 1.0),
+            QuizAttempt::from_string("ABD", 1.0),
+        ];
+
+        assert_eq!(QuizAttempt::validate_uniform_length(&attempts[..1]), Ok(4));
+        assert_eq!(QuizAttempt::validate_uniform_length(&[]), Ok(0));
+        assert_eq!(
+            QuizAttempt::validate_uniform_length(&attempts),
+            Err(LengthMismatch {
+                expected: 4,
+                lengths: vec![(2, vec![3]), (3, vec![1, 4]), (4, vec![0, 2])],
+            })
+        );
+    }
+
+    #[test]
+    fn histogram_counts_answers() {
+        let attempts = vec![
+            QuizAttempt::from_string("ABX", 1.0),
+            QuizAttempt::from_string("ACX", 1.0),
+            QuizAttempt::from_string("BCD", 1.0),
+        ];
+        let histogram = QuizAttempt::answer_histogram(&attempts).unwrap();
+
+        assert_eq!(
+            histogram[0].iter().collect::<Vec<_>>(),
+            vec![(&Answer::A, &2), (&Answer::B, &1)]
+        );
+        assert_eq!(histogram[2][&Answer::X], 2);
+        assert_eq!(
+            QuizAttempt::observed_alphabet(&attempts),
+            [Answer::A, Answer::B, Answer::C, Answer::D, Answer::X].into()
+        );
+        assert!(QuizAttempt::answer_histogram(&[
+            QuizAttempt::from_string("AB", 1.0),
+            QuizAttempt::from_string("A", 1.0)
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn discrimination_follows_the_scores() {
+        let attempts = vec![
+            QuizAttempt::from_string("ABCD", 4.0),
+            QuizAttempt::from_string("ABCA", 3.0),
+            QuizAttempt::from_string("ACDA", 2.0),
+            QuizAttempt::from_string("ACXB", 1.0),
+        ];
+        assert_eq!(
+            QuizAttempt::plurality_answers(&attempts).unwrap(),
+            vec![
+                Some(Answer::A),
+                Some(Answer::B),
+                Some(Answer::C),
+                Some(Answer::A)
+            ]
+        );
+
+        let r = QuizAttempt::question_discrimination(&attempts).unwrap();
+        // everyone gave A to question 1
+        assert_eq!(r[0], 0.0);
+        assert!((r[1] - 0.894).abs() < 0.001);
+        assert!(r[1] > r[3] && r[2] > r[3]);
+        assert!(r.iter().all(|r| (-1.0..=1.0).contains(r)));
+    }
+
+    #[test]
+    fn top_scorers_agree() {
+        let attempts = vec![
+            QuizAttempt::from_string("ABCD", 4.0),
+            QuizAttempt::from_string("ABCA", 3.0),
+            QuizAttempt::from_string("ACDA", 2.0),
+            QuizAttempt::from_string("ACXB", 1.0),
+        ];
+        // only the two above the median count, and they split on question 4
+        assert_eq!(
+            QuizAttempt::agreed_answers(&attempts, 0.9).unwrap(),
+            vec![Some(Answer::A), Some(Answer::B), Some(Answer::C), None]
+        );
+        assert_eq!(
+            QuizAttempt::agreed_answers(&attempts, 0.5).unwrap()[3],
+            Some(Answer::D)
+        );
+        assert!(
+            QuizAttempt::agreed_answers(&[QuizAttempt::from_string("AX", 0.0)], 0.5)
+                .unwrap()
+                .iter()
+                .all(Option::is_none)
+        );
+    }
+
+    #[test]
+    fn best_seed_prefers_fewer_candidates() {
+        let attempts = vec![
+            QuizAttempt::from_string("ABCD", 3.0),
+            QuizAttempt::from_string("AXCD", 3.0),
+            QuizAttempt::from_string("DXCD", 3.0),
+            QuizAttempt::from_string("ABCA", 2.0),
+        ];
+
+        // 4 keys for a blank mistake against 12 for ABCD, the first tie wins
+        assert_eq!(QuizAttempt::best_seed(&attempts).as_string(), "AXCD");
+        assert_eq!(
+            QuizAttempt::best_seed_with(&attempts, &Choices::uniform(&DEFAULT_CHOICES, 4))
+                .as_string(),
+            "AXCD"
+        );
+    }
+
+    #[test]
+    fn per_question_choices() {
+        let att = QuizAttempt::from_string("AEB", 1.0);
+        let choices = Choices::per_question(vec![
+            vec![Answer::A, Answer::B],
+            vec![Answer::A, Answer::B, Answer::C, Answer::D, Answer::E],
+            vec![Answer::A, Answer::B, Answer::C],
+        ]);
+
+        let keys = att.generate_valid_set(&choices).unwrap();
+        assert!(keys.keys().iter().all(|k| choices.allows(k.answers())));
+        // Q1 right: 4 * 2 alternatives, Q2 right: 1 * 2, Q3 right: 1 * 4
+        assert_eq!(keys.keys().len(), 8 + 2 + 4);
+    }
+
+    #[test]
+    fn known_answers_fix_questions() {
+        let att = QuizAttempt::from_string("AEB", 1.0);
+        let choices = Choices::per_question(vec![
+            vec![Answer::A, Answer::B],
+            vec![Answer::A, Answer::B, Answer::C, Answer::D, Answer::E],
+            vec![Answer::A, Answer::B, Answer::C],
+        ]);
+
+        let known = choices.clone().with_known(&[(1, Answer::C)]);
+        assert_eq!(known.at(1), &[Answer::C]);
+        // Q1 right: 2 alternatives for Q3, Q3 right: 1 for Q1
+        assert_eq!(att.generate_valid_set(&known).unwrap().len(), 2 + 1);
+
+        let impossible = choices.with_known(&[(0, Answer::C)]);
+        assert!(att.generate_valid_set(&impossible).unwrap().is_empty());
+    }
+
+    #[test]
+    fn true_false_questions() {
+        let att = QuizAttempt::from_string("ATFC", 2.0);
+        let choices = Choices::per_question(vec![
+            DEFAULT_CHOICES.to_vec(),
+            TRUE_FALSE.to_vec(),
+            TRUE_FALSE.to_vec(),
+            DEFAULT_CHOICES.to_vec(),
+        ]);
+
+        let keys = att.generate_valid_set(&choices).unwrap();
+        assert!(keys.iter().all(|k| choices.allows(k.answers())));
+        assert!(keys.iter().any(|k| k.as_string() == "AFTC"));
+        // C(4, 2) mistake sets, with 1 way to get a T/F question wrong and 3 otherwise
+        assert_eq!(keys.len(), 1 + 3 + 3 + 3 + 3 + 9);
+        assert_eq!(att.estimate_candidates_with(&choices), keys.len() as u128);
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn sampled_keys_are_valid() {
+        let att = QuizAttempt::from_string("ABCDABCDAB", 6.0);
+        let choices = Choices::uniform(&DEFAULT_CHOICES, 10);
+        let all: HashSet<AnswerKey> = att
+            .generate_valid_set(&choices)
+            .unwrap()
+            .into_iter()
+            .collect();
+
+        let sample = att.sample_valid_keys(&choices, 50, 7).unwrap();
+        assert!(!sample.is_empty() && sample.len() <= 50);
+        assert!(sample.iter().all(|k| all.contains(k)));
+        assert_eq!(
+            sample.keys(),
+            att.sample_valid_keys(&choices, 50, 7).unwrap().keys()
+        );
+
+        // blanks are missed in every key, and tolerance widens the sample
+        let attempts = [
+            QuizAttempt::from_string("ABXDABCDXB", 6.0),
+            QuizAttempt::from_string("ABCDABCDAB", 5.0).with_tolerance(1),
+        ];
+        for att in &attempts {
+            let sample = att.sample_valid_keys(&choices, 200, 3).unwrap();
+            assert!(!sample.is_empty());
+            assert!(sample
+                .iter()
+                .all(|k| k.consistent_with(std::slice::from_ref(att))));
+        }
+        let scores: HashSet<i32> = attempts[1]
+            .sample_valid_keys(&choices, 200, 3)
+            .unwrap()
+            .iter()
+            .map(|k| attempts[1].score_against(k))
+            .collect();
+        assert_eq!(scores.len(), 3);
+    }
+
+    #[test]
+    fn penalty_scoring() {
+        let scheme = ScoringScheme {
+            correct: 1.0,
+            wrong: -0.25,
+            blank: 0.0,
+            weights: vec![],
+        };
+        // 2 right, 1 wrong, 1 blank
+        let att = QuizAttempt::try_from_string_with("ABCX", 1.75, scheme.clone()).unwrap();
+
+        assert!(att.check(&AnswerKey::from(vec![
+            Answer::A,
+            Answer::B,
+            Answer::D,
+            Answer::D
+        ])));
+        assert!(!att.check(&AnswerKey::from(vec![
+            Answer::A,
+            Answer::B,
+            Answer::C,
+            Answer::D
+        ])));
+        assert_eq!(
+            att.generate_valid_set(&Choices::uniform(&DEFAULT_CHOICES, 4))
+                .unwrap()
+                .keys()
+                .len(),
+            3 * 3 * 4
+        );
+        assert!(QuizAttempt::try_from_string_with("ABCX", 1.5, scheme).is_err());
+    }
+
+    #[test]
+    fn weighted_questions() {
+        let scheme = ScoringScheme {
+            weights: vec![1, 2, 3],
+            ..ScoringScheme::default()
+        };
+        let weighted = |answers: &str, score: f64| {
+            QuizAttempt::try_from_string_with(answers, score, scheme.clone()).unwrap()
+        };
+        let choices = Choices::uniform(&DEFAULT_CHOICES, 3);
+
+        // 4 points can only be questions 1 and 3
+        let att = weighted("ABC", 4.0);
+        assert!(att.check(&"AAC".parse().unwrap()));
+        assert!(!att.check(&"ABA".parse().unwrap()));
+        assert_eq!(att.mistake_position_sets(), 1);
+        assert_eq!(
+            att.generate_valid_set(&choices).unwrap().to_string(),
+            "AAC\nACC\nADC"
+        );
+        assert_eq!(att.implied_score(&"ABA".parse().unwrap()), 3.0);
+
+        // 3 points is either question 3 alone or questions 1 and 2
+        let att = weighted("ABC", 3.0);
+        let keys = att.generate_valid_set(&choices).unwrap();
+        let brute_force = (0..3)
+            .map(|_| DEFAULT_CHOICES.iter().copied())
+            .multi_cartesian_product()
+            .filter(|key| att.check(&AnswerKey::from(key.clone())))
+            .count();
+        assert_eq!(att.mistake_position_sets(), 2);
+        assert_eq!(keys.len(), 3 + 9);
+        assert_eq!(keys.len(), brute_force);
+        assert_eq!(att.estimate_candidates(4), keys.len() as u128);
+
+        assert_eq!(
+            QuizAttempt::try_from_string_with("ABCD", 3.0, scheme.clone()),
+            Err(ParseError::WeightsMismatch { weights: 3, len: 4 })
+        );
+        assert_eq!(
+            QuizAttempt::try_from_string_with(
+                "ABC",
+                3.0,
+                ScoringScheme {
+                    weights: vec![1, -2, 3],
+                    ..ScoringScheme::default()
+                }
+            ),
+            Err(ParseError::InvalidWeight(-2))
+        );
+        assert!(QuizAttempt::try_from_string_with("ABC", 7.0, scheme).is_err());
+    }
+
+    #[test]
+    fn multi_answer_keys() {
+        let key: AnswerKey = "{A}{B,C}{D}".parse().unwrap();
+        assert_eq!(key.as_string(), "A{B,C}D");
+        assert!(QuizAttempt::from_string("ABD", 3.0).check(&key));
+        assert!(QuizAttempt::from_string("ACD", 3.0).check(&key));
+        assert!(QuizAttempt::from_string("AAD", 2.0).check(&key));
+        assert!(!QuizAttempt::from_string("ACD", 2.0).check(&key));
+
+        let att = QuizAttempt::from_string("AB", 2.0);
+        let choices = Choices::uniform(&[Answer::A, Answer::B, Answer::C], 2);
+        assert_eq!(
+            att.generate_multi_answer_set(&choices, 0)
+                .unwrap()
+                .keys()
+                .len(),
+            1
+        );
+        // each question can also accept B, C or both (A, C or both)
+        assert_eq!(
+            att.generate_multi_answer_set(&choices, 1)
+                .unwrap()
+                .keys()
+                .len(),
+            7
+        );
+        assert_eq!(
+            att.generate_multi_answer_set(&choices, 2)
+                .unwrap()
+                .keys()
+                .len(),
+            16
+        );
+
+        let att = QuizAttempt::from_string("ABC", 2.0);
+        let choices = Choices::uniform(&DEFAULT_CHOICES, 3);
+        let keys = att.generate_multi_answer_set(&choices, 2).unwrap();
+        let unique: HashSet<&AnswerKey> = keys.keys().iter().collect();
+        assert_eq!(unique.len(), keys.keys().len());
+        assert!(keys.keys().iter().all(|k| att.check(k)));
+        assert!(keys.keys().iter().any(|k| k.is_multi_answer()));
+
+        let sets: Vec<Vec<Answer>> = DEFAULT_CHOICES
+            .iter()
+            .copied()
+            .powerset()
+            .filter(|set| !set.is_empty())
+            .collect();
+        let brute_force = (0..3)
+            .map(|_| sets.iter())
+            .multi_cartesian_product()
+            .filter(|sets| sets.iter().filter(|set| set.len() > 1).count() <= 2)
+            .map(|sets| AnswerKey::with_alternatives(sets.into_iter().cloned().collect()))
+            .filter(|k| att.check(k))
+            .count();
+        assert_eq!(keys.keys().len(), brute_force);
+    }
+}
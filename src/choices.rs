@@ -0,0 +1,57 @@
+use crate::Answer;
+
+/// The answers allowed for each question of a quiz.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Choices {
+    per_question: Vec<Vec<Answer>>,
+}
+
+impl Choices {
+    /// The same `alphabet` for each of `len` questions.
+    pub fn uniform(alphabet: &[Answer], len: usize) -> Choices {
+        Choices::per_question(vec![alphabet.to_vec(); len])
+    }
+
+    pub fn per_question(mut per_question: Vec<Vec<Answer>>) -> Choices {
+        // repeated letters would make candidate generation yield duplicate keys
+        for allowed in per_question.iter_mut() {
+            let mut seen = vec![];
+            allowed.retain(|ans| {
+                let new = !seen.contains(ans);
+                seen.push(*ans);
+                new
+            });
+        }
+        Choices { per_question }
+    }
+
+    pub fn len(&self) -> usize {
+        self.per_question.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.per_question.is_empty()
+    }
+
+    pub fn at(&self, question: usize) -> &[Answer] {
+        &self.per_question[question]
+    }
+
+    /// The same choices except that each question in `known` only allows its
+    /// known answer, or nothing if that was not allowed to begin with. Panics
+    /// if a question is out of range.
+    pub fn with_known(mut self, known: &[(usize, Answer)]) -> Choices {
+        for &(q, ans) in known {
+            self.per_question[q].retain(|&allowed| allowed == ans);
+        }
+        self
+    }
+
+    /// Whether every answer in `answers` is allowed for its question.
+    pub fn allows(&self, answers: &[Answer]) -> bool {
+        answers
+            .iter()
+            .zip(&self.per_question)
+            .all(|(ans, allowed)| allowed.contains(ans))
+    }
+}
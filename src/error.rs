@@ -0,0 +1,245 @@
+use core::fmt;
+use std::io;
+
+use crate::Score;
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct InvalidAnswerChar(pub char);
+
+impl fmt::Display for InvalidAnswerChar {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid letter: {}", self.0)
+    }
+}
+
+impl std::error::Error for InvalidAnswerChar {}
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum ParseError {
+    InvalidLetter(char),
+    ImpossibleScore {
+        score: Score,
+        len: usize,
+    },
+    /// The score needs more correct answers than there are non-blank
+    /// answers, which can't happen as a blank never matches the key.
+    MoreCorrectThanAnswered {
+        score: Score,
+        answered: usize,
+    },
+    ScoreNotANumber(String),
+    /// A single-field attempt with nothing after the answers.
+    MissingScore(String),
+    /// The scoring scheme has a different number of weights than answers.
+    WeightsMismatch {
+        weights: usize,
+        len: usize,
+    },
+    /// Question weights have to be positive.
+    InvalidWeight(i32),
+    /// A line split into this many fields instead of 2 or 3.
+    FieldCount(usize),
+    NoAnswers,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::InvalidLetter(c) => write!(f, "Invalid letter: {}", c),
+            ParseError::ImpossibleScore { score, len } => {
+                write!(f, "Impossible score: {} with test length {}", score, len)
+            }
+            ParseError::MoreCorrectThanAnswered { score, answered } => write!(
+                f,
+                "Impossible score: {} needs more correct answers than the {} given (blanks are never correct)",
+                score, answered
+            ),
+            ParseError::ScoreNotANumber(s) => write!(f, "Score is not a number! {}", s),
+            ParseError::MissingScore(s) => write!(f, "No score after the answers: {}", s),
+            ParseError::WeightsMismatch { weights, len } => write!(
+                f,
+                "{} question weights given for a test of length {}",
+                weights, len
+            ),
+            ParseError::InvalidWeight(w) => write!(f, "Question weights must be positive: {}", w),
+            ParseError::FieldCount(n) => write!(
+                f,
+                "Expected answers and a score, optionally after an id, but found {} fields",
+                n
+            ),
+            ParseError::NoAnswers => write!(f, "No answers given"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl From<InvalidAnswerChar> for ParseError {
+    fn from(value: InvalidAnswerChar) -> ParseError {
+        ParseError::InvalidLetter(value.0)
+    }
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum SolveError {
+    /// Reducing by the attempt at this index left no candidate keys.
+    NoConsistentKey { attempt: usize },
+    /// The attempt claims more correct answers than it has, which the
+    /// constructors rule out but the search checks again rather than trust.
+    ScoreExceedsLength { score: Score, len: usize },
+}
+
+impl fmt::Display for SolveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SolveError::NoConsistentKey { attempt } => write!(
+                f,
+                "No answer key is consistent with attempt #{} and those before it",
+                attempt + 1
+            ),
+            SolveError::ScoreExceedsLength { score, len } => write!(
+                f,
+                "A score of {} is more than {} answers could get",
+                score, len
+            ),
+        }
+    }
+}
+
+// scores are always finite
+impl Eq for SolveError {}
+
+impl std::error::Error for SolveError {}
+
+/// Attempts that do not all have the same number of answers.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct LengthMismatch {
+    /// The most common length.
+    pub expected: usize,
+    /// Each length found, with the 0-based indices of the attempts having it,
+    /// shortest first.
+    pub lengths: Vec<(usize, Vec<usize>)>,
+}
+
+impl LengthMismatch {
+    // the message, calling what the indices stand for `what` and showing
+    // each of them with `number`
+    fn write_with(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        what: &str,
+        number: impl Fn(usize) -> String,
+    ) -> fmt::Result {
+        write!(
+            f,
+            "The lengths of the answers are not all the same! Most have {} answers, but:",
+            self.expected
+        )?;
+        for (len, indices) in self.lengths.iter().filter(|(len, _)| *len != self.expected) {
+            let numbers = indices.iter().map(|&i| number(i));
+            write!(
+                f,
+                "\n  {} {} have {}",
+                what,
+                numbers.collect::<Vec<_>>().join(", "),
+                len
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for LengthMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_with(f, "attempt(s)", |i| format!("#{}", i + 1))
+    }
+}
+
+impl std::error::Error for LengthMismatch {}
+
+/// A line of input that could not be parsed.
+#[derive(PartialEq, Debug, Clone)]
+pub struct LineError {
+    /// 1-based line number.
+    pub line: usize,
+    pub content: String,
+    pub error: ParseError,
+}
+
+impl fmt::Display for LineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {} ({:?})", self.line, self.error, self.content)
+    }
+}
+
+#[derive(Debug)]
+pub enum LoadError {
+    Io(io::Error),
+    Parse(Vec<LineError>),
+    /// Lines whose answers differ in length from the rest, as a
+    /// [`LengthMismatch`] holding 1-based line numbers instead of attempt
+    /// indices.
+    LengthMismatch(LengthMismatch),
+    /// Lines whose answers differ in length from the declared
+    /// [`crate::LoadOptions::length`], as `(line number, length)` pairs.
+    WrongLength {
+        declared: usize,
+        lines: Vec<(usize, usize)>,
+    },
+    NoAttempts,
+    /// Separate answers and scores inputs of different lengths.
+    LineCountMismatch {
+        answers: usize,
+        scores: usize,
+    },
+    #[cfg(feature = "serde")]
+    Json(serde_json::Error),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Io(e) => write!(f, "Could not read input: {}", e),
+            LoadError::Parse(errors) => {
+                write!(f, "Could not parse {} line(s):", errors.len())?;
+                for e in errors {
+                    write!(f, "\n  {}", e)?;
+                }
+                Ok(())
+            }
+            LoadError::LengthMismatch(mismatch) => {
+                mismatch.write_with(f, "line(s)", |line| line.to_string())
+            }
+            LoadError::WrongLength { declared, lines } => {
+                write!(f, "The quiz has {} questions, but:", declared)?;
+                for (line, len) in lines {
+                    write!(f, "\n  line {} has {} answers", line, len)?;
+                }
+                Ok(())
+            }
+            LoadError::NoAttempts => write!(f, "No attempts found in the input"),
+            LoadError::LineCountMismatch { answers, scores } => write!(
+                f,
+                "There are {} lines of answers but {} lines of scores",
+                answers, scores
+            ),
+            #[cfg(feature = "serde")]
+            LoadError::Json(e) => write!(f, "Could not parse JSON input: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+impl From<io::Error> for LoadError {
+    fn from(value: io::Error) -> LoadError {
+        LoadError::Io(value)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<serde_json::Error> for LoadError {
+    fn from(value: serde_json::Error) -> LoadError {
+        LoadError::Json(value)
+    }
+}
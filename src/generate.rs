@@ -0,0 +1,77 @@
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+use crate::{Answer, AnswerKey, Choices, QuizAttempt};
+
+/// A random key allowed by `choices` and `attempts` answered against it, each
+/// missing every question with the same chance so that there are
+/// `mistakes_mean` wrong answers on average. Attempts are scored one point
+/// per correct answer, and the same `seed` gives the same quiz.
+pub fn synthetic_quiz(
+    choices: &Choices,
+    attempts: usize,
+    mistakes_mean: f64,
+    seed: u64,
+) -> (AnswerKey, Vec<QuizAttempt>) {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let len = choices.len();
+    let key: Vec<Answer> = (0..len)
+        .map(|q| *choices.at(q).choose(&mut rng).expect("No choices!"))
+        .collect();
+    let miss = (mistakes_mean / len as f64).clamp(0.0, 1.0);
+
+    let attempts = (0..attempts)
+        .map(|_| {
+            let mut correct = 0;
+            let answers: Vec<Answer> = (0..len)
+                .map(|q| {
+                    let wrong: Vec<Answer> = choices
+                        .at(q)
+                        .iter()
+                        .filter(|&&c| c != key[q])
+                        .copied()
+                        .collect();
+                    match wrong.choose(&mut rng) {
+                        Some(&ans) if rng.gen_bool(miss) => ans,
+                        _ => {
+                            correct += 1;
+                            key[q]
+                        }
+                    }
+                })
+                .collect();
+            QuizAttempt::new(answers, correct as f64).expect("Scored against the key")
+        })
+        .collect();
+
+    (AnswerKey::from(key), attempts)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{synthetic_quiz, Answer, Choices, DEFAULT_CHOICES, TRUE_FALSE};
+
+    #[test]
+    fn attempts_agree_with_the_key() {
+        let choices = Choices::uniform(&DEFAULT_CHOICES, 20);
+        let (key, attempts) = synthetic_quiz(&choices, 50, 4.0, 7);
+
+        assert_eq!(attempts.len(), 50);
+        assert!(choices.allows(key.answers()));
+        assert!(attempts.iter().all(|att| att.check(&key)));
+        let mistakes: usize = attempts.iter().map(|att| att.num_mistakes()).sum();
+        assert!((2.0..6.0).contains(&(mistakes as f64 / 50.0)));
+
+        let (again, repeated) = synthetic_quiz(&choices, 50, 4.0, 7);
+        assert_eq!((again, repeated), (key, attempts));
+
+        // a single choice leaves nothing to get wrong
+        let (key, attempts) = synthetic_quiz(&Choices::uniform(&[Answer::T], 3), 2, 3.0, 0);
+        assert_eq!(key.as_string(), "TTT");
+        assert!(attempts.iter().all(|att| att.score() == 3.0));
+
+        let (_, attempts) = synthetic_quiz(&Choices::uniform(&TRUE_FALSE, 5), 10, 9.0, 1);
+        assert!(attempts.iter().all(|att| att.score() == 0.0));
+    }
+}
@@ -0,0 +1,784 @@
+use std::collections::HashMap;
+use std::fs::File;
+#[cfg(feature = "serde")]
+use std::io::Read;
+use std::io::{self, BufRead, BufReader};
+
+use crate::answer::is_separator;
+#[cfg(feature = "serde")]
+use crate::Score;
+use crate::{LengthMismatch, LineError, LoadError, ParseError, QuizAttempt, ScoringScheme};
+
+#[derive(Debug, Clone)]
+pub struct LoadOptions {
+    /// Separator between the answers and the score on each line.
+    pub delimiter: char,
+    /// How the recorded scores were awarded.
+    pub scoring: ScoringScheme,
+    /// Drop attempts that repeat an earlier line's answers and score.
+    pub dedup_attempts: bool,
+    /// Fill attempts shorter than the longest one, or than the question
+    /// weights when there are some, with blanks.
+    pub pad_short: bool,
+    /// Character marking an unanswered question, read as `X`.
+    pub blank: char,
+    /// Lines starting with this, leading whitespace aside, are comments.
+    pub comment: Option<char>,
+    /// The number of questions, when known. Attempts with any other number
+    /// of answers are rejected, and short ones padded up to it.
+    pub length: Option<usize>,
+}
+
+impl Default for LoadOptions {
+    fn default() -> LoadOptions {
+        LoadOptions {
+            delimiter: ',',
+            scoring: ScoringScheme::default(),
+            dedup_attempts: false,
+            pad_short: false,
+            blank: 'X',
+            comment: Some('#'),
+            length: None,
+        }
+    }
+}
+
+/// Attempts read from an input, along with what was noticed on the way.
+#[derive(Debug, Clone)]
+pub struct LoadedAttempts {
+    /// Sorted from the highest score down.
+    pub attempts: Vec<QuizAttempt>,
+    /// `(line, earlier line)` for each line repeating an earlier attempt's
+    /// answers and score, which adds no information.
+    pub duplicates: Vec<(usize, usize)>,
+    /// `(line, answers given)` for each attempt filled up with blanks.
+    pub padded: Vec<(usize, usize)>,
+}
+
+pub fn extract_attempts_from_file(
+    filename: &str,
+    options: &LoadOptions,
+) -> Result<Vec<QuizAttempt>, LoadError> {
+    LoadedAttempts::from_file(filename, options).map(|loaded| loaded.attempts)
+}
+
+/// Read `answers,score` lines from any reader, such as a locked stdin.
+/// Lines may start with an identifier column, as in `id,answers,score`.
+/// Blank lines and comments, `#` ones by default, are skipped.
+pub fn extract_attempts_from_reader<R: BufRead>(
+    reader: R,
+    options: &LoadOptions,
+) -> Result<Vec<QuizAttempt>, LoadError> {
+    LoadedAttempts::from_reader(reader, options).map(|loaded| loaded.attempts)
+}
+
+impl LoadedAttempts {
+    /// Files ending in `.gz` are decompressed first, which needs the `gzip`
+    /// feature.
+    pub fn from_file(filename: &str, options: &LoadOptions) -> Result<LoadedAttempts, LoadError> {
+        let f = File::open(filename)?;
+        if filename.ends_with(".gz") {
+            #[cfg(feature = "gzip")]
+            return LoadedAttempts::from_reader(
+                BufReader::new(flate2::read::MultiGzDecoder::new(f)),
+                options,
+            );
+            #[cfg(not(feature = "gzip"))]
+            return Err(LoadError::Io(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "reading .gz files needs answerkey-search to be built with the `gzip` feature",
+            )));
+        }
+        LoadedAttempts::from_reader(BufReader::new(f), options)
+    }
+
+    /// Like [`extract_attempts_from_reader`], also reporting duplicates.
+    pub fn from_reader<R: BufRead>(
+        reader: R,
+        options: &LoadOptions,
+    ) -> Result<LoadedAttempts, LoadError> {
+        load(reader, options)
+    }
+
+    /// Read the answers of each attempt from one file and its score from the
+    /// same line of another, see [`LoadedAttempts::from_parallel_readers`].
+    pub fn from_parallel_files(
+        answers: &str,
+        scores: &str,
+        options: &LoadOptions,
+    ) -> Result<LoadedAttempts, LoadError> {
+        LoadedAttempts::from_parallel_readers(
+            BufReader::new(File::open(answers)?),
+            BufReader::new(File::open(scores)?),
+            options,
+        )
+    }
+
+    /// Pair the lines of `answers` with those of `scores`, which must have as
+    /// many. Lines blank in both are skipped, comments are not as they would
+    /// put the two out of step.
+    pub fn from_parallel_readers<A: BufRead, S: BufRead>(
+        answers: A,
+        scores: S,
+        options: &LoadOptions,
+    ) -> Result<LoadedAttempts, LoadError> {
+        let answers = read_lines(answers)?;
+        let scores = read_lines(scores)?;
+        if answers.len() != scores.len() {
+            return Err(LoadError::LineCountMismatch {
+                answers: answers.len(),
+                scores: scores.len(),
+            });
+        }
+
+        let mut loaded_attempts = vec![];
+        let mut errors = vec![];
+        let mut padded = vec![];
+        let target = options.pad_target(answers.iter().map(String::as_str));
+        for (i, (answers, score)) in answers.iter().zip(&scores).enumerate() {
+            if answers.trim().is_empty() && score.trim().is_empty() {
+                continue;
+            }
+            let (parsed, given) = options.parse_split(vec![answers, score], target);
+            if let Some(given) = given {
+                padded.push((i + 1, given));
+            }
+            match parsed {
+                Ok(att) => loaded_attempts.push((i + 1, att)),
+                Err(error) => errors.push(LineError {
+                    line: i + 1,
+                    content: format!("{}{}{}", answers, options.delimiter, score),
+                    error,
+                }),
+            }
+        }
+        finish(loaded_attempts, errors, padded, options)
+    }
+
+    /// Read a JSON array of `{"answers": "ABCD", "score": 3}` objects, each
+    /// optionally with an `"id"`. Errors and duplicates are reported by
+    /// position in the array, counting from 1, in place of line numbers.
+    #[cfg(feature = "serde")]
+    pub fn from_json_file(
+        filename: &str,
+        options: &LoadOptions,
+    ) -> Result<LoadedAttempts, LoadError> {
+        LoadedAttempts::from_json_reader(BufReader::new(File::open(filename)?), options)
+    }
+
+    /// Like [`LoadedAttempts::from_json_file`], from any reader.
+    #[cfg(feature = "serde")]
+    pub fn from_json_reader<R: Read>(
+        reader: R,
+        options: &LoadOptions,
+    ) -> Result<LoadedAttempts, LoadError> {
+        #[derive(serde::Deserialize)]
+        struct JsonAttempt {
+            id: Option<String>,
+            answers: String,
+            score: Score,
+        }
+
+        let entries: Vec<JsonAttempt> = serde_json::from_reader(reader)?;
+        let mut loaded_attempts = vec![];
+        let mut errors = vec![];
+        let mut padded = vec![];
+        let target = options.pad_target(entries.iter().map(|entry| entry.answers.as_str()));
+        for (i, entry) in entries.into_iter().enumerate() {
+            let (answers, given) = prepare_answers(&entry.answers, options, target);
+            if let Some(given) = given {
+                padded.push((i + 1, given));
+            }
+            match QuizAttempt::try_from_string_with(&answers, entry.score, options.scoring.clone())
+            {
+                Ok(att) => loaded_attempts.push((
+                    i + 1,
+                    match entry.id {
+                        Some(id) => att.with_id(id),
+                        None => att,
+                    },
+                )),
+                Err(error) => errors.push(LineError {
+                    line: i + 1,
+                    content: entry.answers,
+                    error,
+                }),
+            }
+        }
+        finish(loaded_attempts, errors, padded, options)
+    }
+}
+
+impl LoadOptions {
+    /// Parse one `answers,score` or `id,answers,score` line as read from a
+    /// file.
+    pub fn parse_line(&self, line: &str) -> Result<QuizAttempt, ParseError> {
+        let target = self.pad_target(self.answers_field(line));
+        self.parse_fields(line, target).0
+    }
+
+    // blank lines and comments
+    pub(crate) fn skips(&self, line: &str) -> bool {
+        line.trim().is_empty()
+            || self
+                .comment
+                .is_some_and(|c| line.trim_start().starts_with(c))
+    }
+
+    // the answers come just before the score
+    fn answers_field<'a>(&self, line: &'a str) -> Option<&'a str> {
+        let fields: Vec<&str> = line.split(self.delimiter).collect();
+        match fields.len() {
+            2 | 3 => Some(fields[fields.len() - 2]),
+            _ => None,
+        }
+    }
+
+    // How many answers `pad_short` fills attempts up to: the declared length,
+    // else as many as there are weights, else as many as the longest of
+    // `answers` has. The score is checked after padding, so that blanks earn
+    // their points.
+    fn pad_target<'a>(&self, answers: impl IntoIterator<Item = &'a str>) -> usize {
+        if let Some(len) = self.length {
+            return len;
+        }
+        if self.scoring.is_weighted() {
+            return self.scoring.weights.len();
+        }
+        answers
+            .into_iter()
+            .map(|answers| prepare_answers(answers, self, 0).0)
+            .map(|answers| answers.chars().filter(|&c| !is_separator(c)).count())
+            .max()
+            .unwrap_or(0)
+    }
+
+    // also returns how many answers were given when they had to be padded
+    pub(crate) fn parse_fields(
+        &self,
+        line: &str,
+        target: usize,
+    ) -> (Result<QuizAttempt, ParseError>, Option<usize>) {
+        self.parse_split(line.split(self.delimiter).collect(), target)
+    }
+
+    fn parse_split(
+        &self,
+        fields: Vec<&str>,
+        target: usize,
+    ) -> (Result<QuizAttempt, ParseError>, Option<usize>) {
+        let prepared;
+        let mut fields = fields;
+        let mut given = None;
+        // the answers come just before the score
+        if let 2 | 3 = fields.len() {
+            let at = fields.len() - 2;
+            (prepared, given) = prepare_answers(fields[at], self, target);
+            fields[at] = &prepared;
+        }
+        (
+            QuizAttempt::try_from_list_with(&fields, self.scoring.clone()),
+            given,
+        )
+    }
+}
+
+fn load<R: BufRead>(reader: R, options: &LoadOptions) -> Result<LoadedAttempts, LoadError> {
+    let mut loaded_attempts: Vec<(usize, QuizAttempt)> = vec![];
+    let mut errors: Vec<LineError> = vec![];
+    let mut padded = vec![];
+
+    // all lines first, to know how far to pad
+    let lines: Vec<(usize, String)> = read_lines(reader)?
+        .into_iter()
+        .enumerate()
+        .filter(|(_, line)| !options.skips(line))
+        .collect();
+    let target = options.pad_target(lines.iter().filter_map(|(_, l)| options.answers_field(l)));
+    for (i, line) in lines {
+        let (parsed, given) = options.parse_fields(&line, target);
+        if let Some(given) = given {
+            padded.push((i + 1, given));
+        }
+        match parsed {
+            Ok(att) => loaded_attempts.push((i + 1, att)),
+            Err(error) => errors.push(LineError {
+                line: i + 1,
+                content: line,
+                error,
+            }),
+        }
+    }
+
+    finish(loaded_attempts, errors, padded, options)
+}
+
+// Spreadsheet exports often start with a byte order mark, and `lines` leaves
+// a `\r` behind if it is not right before the `\n`.
+pub(crate) fn clean_line(i: usize, line: &str) -> &str {
+    let line = match i {
+        0 => line.strip_prefix('\u{feff}').unwrap_or(line),
+        _ => line,
+    };
+    line.trim_end_matches('\r')
+}
+
+fn read_lines<R: BufRead>(reader: R) -> io::Result<Vec<String>> {
+    reader
+        .lines()
+        .enumerate()
+        .map(|(i, line)| Ok(clean_line(i, &line?).to_string()))
+        .collect()
+}
+
+// Reads the blank marker as `X`, before it could be taken for a separator.
+// With `pad_short`, answers short of `target` are also filled up with
+// blanks, returning how many were given.
+fn prepare_answers(answers: &str, options: &LoadOptions, target: usize) -> (String, Option<usize>) {
+    let answers = answers.trim().replace(options.blank, "X");
+    let given = answers.chars().filter(|&c| !is_separator(c)).count();
+    if !options.pad_short || given >= target {
+        return (answers, None);
+    }
+    (answers + &"X".repeat(target - given), Some(given))
+}
+
+/// Checks and sorting shared by every input format.
+fn finish(
+    loaded_attempts: Vec<(usize, QuizAttempt)>,
+    errors: Vec<LineError>,
+    padded: Vec<(usize, usize)>,
+    options: &LoadOptions,
+) -> Result<LoadedAttempts, LoadError> {
+    if !errors.is_empty() {
+        return Err(LoadError::Parse(errors));
+    }
+    if loaded_attempts.is_empty() {
+        return Err(LoadError::NoAttempts);
+    }
+
+    if let Some(declared) = options.length {
+        let lines: Vec<(usize, usize)> = loaded_attempts
+            .iter()
+            .filter(|(_, att)| att.answers().len() != declared)
+            .map(|(line, att)| (*line, att.answers().len()))
+            .collect();
+        if !lines.is_empty() {
+            return Err(LoadError::WrongLength { declared, lines });
+        }
+    }
+
+    // quiz attempts must all have the same length
+    let (lines, loaded_attempts): (Vec<usize>, Vec<QuizAttempt>) =
+        loaded_attempts.into_iter().unzip();
+    if let Err(mismatch) = QuizAttempt::validate_uniform_length(&loaded_attempts) {
+        return Err(LoadError::LengthMismatch(LengthMismatch {
+            lengths: mismatch
+                .lengths
+                .into_iter()
+                .map(|(len, atts)| (len, atts.into_iter().map(|i| lines[i]).collect()))
+                .collect(),
+            ..mismatch
+        }));
+    }
+
+    let mut first_seen: HashMap<(String, u64), usize> = HashMap::new();
+    let mut duplicates = vec![];
+    let mut attempts = vec![];
+    for (line, att) in lines.into_iter().zip(loaded_attempts) {
+        let seen = (att.as_string(), att.score().to_bits());
+        match first_seen.get(&seen) {
+            Some(&earlier) => {
+                duplicates.push((line, earlier));
+                if !options.dedup_attempts {
+                    attempts.push(att);
+                }
+            }
+            None => {
+                first_seen.insert(seen, line);
+                attempts.push(att);
+            }
+        }
+    }
+
+    // sort by score
+    attempts.sort();
+    attempts.reverse();
+
+    Ok(LoadedAttempts {
+        attempts,
+        duplicates,
+        padded,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        extract_attempts_from_reader, LineError, LoadError, LoadOptions, LoadedAttempts,
+        ParseError, ScoringScheme,
+    };
+
+    #[test]
+    fn reads_and_sorts_attempts() {
+        let attempts = extract_attempts_from_reader(
+            "ABCA,2\nABCD,3\nDDDD,1\n".as_bytes(),
+            &LoadOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            attempts.iter().map(|att| att.score()).collect::<Vec<f64>>(),
+            vec![3.0, 2.0, 1.0]
+        );
+    }
+
+    #[test]
+    fn custom_delimiter_and_whitespace() {
+        let options = LoadOptions {
+            delimiter: ';',
+            ..LoadOptions::default()
+        };
+        let attempts =
+            extract_attempts_from_reader("ABCD ; 3\n abca;2 \n".as_bytes(), &options).unwrap();
+
+        assert_eq!(attempts.len(), 2);
+        assert_eq!(attempts[0].score(), 3.0);
+        assert_eq!(attempts[1].answers().len(), 4);
+    }
+
+    #[test]
+    fn reads_student_ids() {
+        let attempts = extract_attempts_from_reader(
+            "s001,ABCD,3\nABCA,2\ns042 , DDDD ,1\n".as_bytes(),
+            &LoadOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            attempts.iter().map(|att| att.id()).collect::<Vec<_>>(),
+            vec![Some("s001"), None, Some("s042")]
+        );
+    }
+
+    #[test]
+    fn reports_and_drops_duplicates() {
+        let input = "ABCD,3\nABCA,2\nabcd, 3.0\nABCD,2\nABCA,2\n";
+        let loaded =
+            LoadedAttempts::from_reader(input.as_bytes(), &LoadOptions::default()).unwrap();
+        assert_eq!(loaded.duplicates, vec![(3, 1), (5, 2)]);
+        assert_eq!(loaded.attempts.len(), 5);
+
+        let options = LoadOptions {
+            dedup_attempts: true,
+            ..LoadOptions::default()
+        };
+        let loaded = LoadedAttempts::from_reader(input.as_bytes(), &options).unwrap();
+        assert_eq!(loaded.duplicates.len(), 2);
+        assert_eq!(loaded.attempts.len(), 3);
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn reads_gzipped_files() {
+        use std::io::Write;
+
+        let path = std::env::temp_dir().join(format!("attempts-{}.txt.gz", std::process::id()));
+        let mut gz = flate2::write::GzEncoder::new(
+            std::fs::File::create(&path).unwrap(),
+            flate2::Compression::default(),
+        );
+        gz.write_all(b"ABCA,2\nABCD,3\n").unwrap();
+        gz.finish().unwrap();
+
+        let attempts =
+            crate::extract_attempts_from_file(path.to_str().unwrap(), &LoadOptions::default());
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(attempts.unwrap()[0].as_string(), "ABCD");
+    }
+
+    #[test]
+    fn reads_windows_spreadsheet_exports() {
+        let path = std::env::temp_dir().join(format!("attempts-{}-bom.csv", std::process::id()));
+        std::fs::write(&path, "\u{feff}ABCA,2\r\nABCD,3\r\r\n").unwrap();
+
+        let attempts =
+            crate::extract_attempts_from_file(path.to_str().unwrap(), &LoadOptions::default());
+        std::fs::remove_file(&path).unwrap();
+        let attempts = attempts.unwrap();
+        assert_eq!(attempts[0].as_string(), "ABCD");
+        assert_eq!(attempts[1].as_string(), "ABCA");
+
+        let loaded = LoadedAttempts::from_parallel_readers(
+            "\u{feff}ABCD\r\nABCA\r\n".as_bytes(),
+            "\u{feff}3\r\n2\r\n".as_bytes(),
+            &LoadOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(loaded.attempts[0].as_string(), "ABCD");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn reads_json_attempts() {
+        let input = r#"[
+            {"answers": "ABCA", "score": 2},
+            {"id": "s001", "answers": "abcd", "score": 3.0}
+        ]"#;
+        let loaded =
+            LoadedAttempts::from_json_reader(input.as_bytes(), &LoadOptions::default()).unwrap();
+        assert_eq!(loaded.attempts[0].id(), Some("s001"));
+        assert_eq!(loaded.attempts[0].as_string(), "ABCD");
+        assert_eq!(loaded.attempts[1].score(), 2.0);
+
+        let mismatched = r#"[{"answers": "ABCA", "score": 2}, {"answers": "AB", "score": 1}]"#;
+        assert!(matches!(
+            LoadedAttempts::from_json_reader(mismatched.as_bytes(), &LoadOptions::default()),
+            Err(LoadError::LengthMismatch(_))
+        ));
+        assert!(matches!(
+            LoadedAttempts::from_json_reader(
+                r#"[{"answers": "AB"}]"#.as_bytes(),
+                &LoadOptions::default()
+            ),
+            Err(LoadError::Json(_))
+        ));
+    }
+
+    #[test]
+    fn skips_blank_lines() {
+        let attempts = extract_attempts_from_reader(
+            "ABCD,3\n\n  \nABCA,2\n\n".as_bytes(),
+            &LoadOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(attempts.len(), 2);
+    }
+
+    #[test]
+    fn reports_every_malformed_line() {
+        let result = extract_attempts_from_reader(
+            "ABCD,3\nABCD\nABCD,x\n,\nABCD,1,2,3\n".as_bytes(),
+            &LoadOptions::default(),
+        );
+
+        match result {
+            Err(LoadError::Parse(errors)) => {
+                assert_eq!(
+                    errors,
+                    vec![
+                        LineError {
+                            line: 2,
+                            content: "ABCD".to_string(),
+                            error: ParseError::MissingScore("ABCD".to_string()),
+                        },
+                        LineError {
+                            line: 3,
+                            content: "ABCD,x".to_string(),
+                            error: ParseError::ScoreNotANumber("x".to_string()),
+                        },
+                        LineError {
+                            line: 4,
+                            content: ",".to_string(),
+                            error: ParseError::ScoreNotANumber("".to_string()),
+                        },
+                        LineError {
+                            line: 5,
+                            content: "ABCD,1,2,3".to_string(),
+                            error: ParseError::FieldCount(4),
+                        },
+                    ]
+                );
+            }
+            other => panic!("Expected parse errors, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn pads_short_attempts() {
+        let options = LoadOptions {
+            pad_short: true,
+            ..LoadOptions::default()
+        };
+        let loaded =
+            LoadedAttempts::from_reader("ABCD,3\nAB,1\nABC,2\n".as_bytes(), &options).unwrap();
+        assert_eq!(
+            loaded
+                .attempts
+                .iter()
+                .map(|att| att.as_string())
+                .collect::<Vec<_>>(),
+            vec!["ABCD", "ABCX", "ABXX"]
+        );
+        assert_eq!(loaded.padded, vec![(2, 2), (3, 3)]);
+
+        let weighted = LoadOptions {
+            scoring: ScoringScheme {
+                weights: vec![1, 2, 1],
+                ..ScoringScheme::default()
+            },
+            ..options
+        };
+        let loaded = LoadedAttempts::from_reader("A B,3\n".as_bytes(), &weighted).unwrap();
+        assert_eq!(loaded.attempts[0].as_string(), "ABX");
+        assert_eq!(loaded.padded, vec![(1, 2)]);
+
+        // the score is only reachable with the padded blanks
+        let half_blanks = LoadOptions {
+            scoring: ScoringScheme {
+                blank: 0.5,
+                ..ScoringScheme::default()
+            },
+            ..options
+        };
+        let loaded =
+            LoadedAttempts::from_reader("ABCD,2\nAB,3\n".as_bytes(), &half_blanks).unwrap();
+        let short = loaded.attempts.iter().find(|att| att.score() == 3.0);
+        assert_eq!(short.unwrap().as_string(), "ABXX");
+        assert_eq!(loaded.padded, vec![(2, 2)]);
+    }
+
+    #[test]
+    fn skips_comments() {
+        let input = "# section A\nABCD,3\n\n  # section B\nABCA,2\n";
+        let loaded =
+            LoadedAttempts::from_reader(input.as_bytes(), &LoadOptions::default()).unwrap();
+        assert_eq!(loaded.attempts.len(), 2);
+
+        let options = LoadOptions {
+            comment: Some(';'),
+            ..LoadOptions::default()
+        };
+        let attempts = extract_attempts_from_reader("; ABCD,3\nABCA,2\n".as_bytes(), &options);
+        assert_eq!(attempts.unwrap().len(), 1);
+
+        let options = LoadOptions {
+            comment: None,
+            ..LoadOptions::default()
+        };
+        match extract_attempts_from_reader(input.as_bytes(), &options) {
+            Err(LoadError::Parse(errors)) => assert_eq!(errors[0].line, 1),
+            other => panic!("Expected a parse error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn reads_parallel_answers_and_scores() {
+        let loaded = LoadedAttempts::from_parallel_readers(
+            "ABCD\nA.CA\n\nABBD\n".as_bytes(),
+            "3\n1\n\n2\n".as_bytes(),
+            &LoadOptions {
+                blank: '.',
+                ..LoadOptions::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            loaded
+                .attempts
+                .iter()
+                .map(|att| (att.as_string(), att.correct()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("ABCD".to_string(), 3),
+                ("ABBD".to_string(), 2),
+                ("AXCA".to_string(), 1)
+            ]
+        );
+
+        match LoadedAttempts::from_parallel_readers(
+            "ABCD\nABCA\n".as_bytes(),
+            "3\n".as_bytes(),
+            &LoadOptions::default(),
+        ) {
+            Err(LoadError::LineCountMismatch { answers, scores }) => {
+                assert_eq!((answers, scores), (2, 1))
+            }
+            other => panic!("Expected a line count mismatch, got {:?}", other),
+        }
+        match LoadedAttempts::from_parallel_readers(
+            "ABCD\nABCA\n".as_bytes(),
+            "3\nthree\n".as_bytes(),
+            &LoadOptions::default(),
+        ) {
+            Err(LoadError::Parse(errors)) => {
+                assert_eq!(errors[0].line, 2);
+                assert_eq!(errors[0].content, "ABCA,three");
+            }
+            other => panic!("Expected a parse error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn reads_custom_blanks() {
+        let options = LoadOptions {
+            blank: '.',
+            ..LoadOptions::default()
+        };
+        let att = options.parse_line("s1,A.CD,2").unwrap();
+        assert_eq!(att.as_string(), "AXCD");
+        assert_eq!(att.id(), Some("s1"));
+
+        // the score's sign is not a blank
+        let options = LoadOptions {
+            blank: '-',
+            scoring: ScoringScheme {
+                wrong: -0.5,
+                ..ScoringScheme::default()
+            },
+            ..LoadOptions::default()
+        };
+        let attempts = extract_attempts_from_reader("A-C-,-1\n".as_bytes(), &options).unwrap();
+        assert_eq!(attempts[0].as_string(), "AXCX");
+    }
+
+    #[test]
+    fn reports_mismatched_lengths() {
+        let result = extract_attempts_from_reader(
+            "ABCD,3\nABC,2\nABCA,2\nABCDA,1\n".as_bytes(),
+            &LoadOptions::default(),
+        );
+
+        match result {
+            Err(LoadError::LengthMismatch(mismatch)) => {
+                assert_eq!(mismatch.expected, 4);
+                assert_eq!(
+                    mismatch.lengths,
+                    vec![(3, vec![2]), (4, vec![1, 3]), (5, vec![4])]
+                );
+                assert!(mismatch.to_string().contains("attempt(s) #3 have 3"));
+                assert!(LoadError::LengthMismatch(mismatch)
+                    .to_string()
+                    .ends_with("but:\n  line(s) 2 have 3\n  line(s) 4 have 5"));
+            }
+            other => panic!("Expected a length mismatch, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn checks_the_declared_length() {
+        let options = LoadOptions {
+            length: Some(4),
+            ..LoadOptions::default()
+        };
+        let truncated = extract_attempts_from_reader("ABC,3\nABC,2\nABCA,2\n".as_bytes(), &options);
+        match truncated {
+            Err(LoadError::WrongLength { declared, lines }) => {
+                assert_eq!(declared, 4);
+                assert_eq!(lines, vec![(1, 3), (2, 3)]);
+            }
+            other => panic!("Expected a wrong length, got {:?}", other),
+        }
+        assert!(extract_attempts_from_reader("ABCD,3\n".as_bytes(), &options).is_ok());
+
+        let padding = LoadOptions {
+            pad_short: true,
+            length: Some(5),
+            ..options
+        };
+        let loaded = LoadedAttempts::from_reader("ABC,3\nABCD,2\n".as_bytes(), &padding).unwrap();
+        assert_eq!(loaded.attempts[0].as_string(), "ABCXX");
+        assert_eq!(loaded.padded, vec![(1, 3), (2, 4)]);
+    }
+}
@@ -0,0 +1,1492 @@
+use core::fmt;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+use itertools::Itertools;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+use crate::answer::is_separator;
+use crate::{
+    Answer, Choices, LengthMismatch, LineError, LoadError, LoadOptions, ParseError, QuizAttempt,
+    SolveError, DEFAULT_CHOICES,
+};
+
+/// Create `filename` for writing, and any of its parent directories that are
+/// missing.
+pub(crate) fn create_output(filename: &str) -> io::Result<File> {
+    if let Some(parent) = Path::new(filename).parent() {
+        fs::create_dir_all(parent)?;
+    }
+    File::create(filename)
+}
+
+// Every key that differs from `base` at exactly `positions`, each of them
+// holding a choice other than the one in `base`.
+pub(crate) fn keys_differing_at(
+    base: &[Answer],
+    choices: &Choices,
+    positions: Vec<usize>,
+) -> impl Iterator<Item = AnswerKey> {
+    // every position has to hold a letter other than the one in `base`
+    let alternatives: Vec<Vec<Answer>> = positions
+        .iter()
+        .map(|&pos| {
+            choices
+                .at(pos)
+                .iter()
+                .filter(|&&c| c != base[pos])
+                .copied()
+                .collect()
+        })
+        .collect();
+
+    // step through the alternatives like an odometer, changing only the
+    // positions that roll over instead of rebuilding the key each time
+    let mut this_key = base.to_vec();
+    for (&pos, alts) in positions.iter().zip(&alternatives) {
+        if let Some(&first) = alts.first() {
+            this_key[pos] = first;
+        }
+    }
+    let mut digits = vec![0; positions.len()];
+    let mut done = alternatives.iter().any(Vec::is_empty);
+
+    std::iter::from_fn(move || {
+        if done {
+            return None;
+        }
+        let current = AnswerKey::from(this_key.clone());
+
+        done = true;
+        for ((digit, &pos), alts) in digits.iter_mut().zip(&positions).zip(&alternatives) {
+            *digit += 1;
+            if *digit < alts.len() {
+                this_key[pos] = alts[*digit];
+                done = false;
+                break;
+            }
+            *digit = 0;
+            this_key[pos] = alts[0];
+        }
+
+        Some(current)
+    })
+}
+
+// Compares answer by answer, ranking answers as in `order` and any not in it
+// after those, falling back to the usual order of keys.
+fn cmp_in_order(a: &AnswerKey, b: &AnswerKey, order: &[Answer]) -> Ordering {
+    let rank = |ans: &Answer| {
+        (
+            order.iter().position(|o| o == ans).unwrap_or(order.len()),
+            *ans,
+        )
+    };
+    a.answers
+        .iter()
+        .map(rank)
+        .cmp(b.answers.iter().map(rank))
+        .then_with(|| a.cmp(b))
+}
+
+#[derive(PartialEq, PartialOrd, Ord, Eq, Hash, Debug, Clone)]
+pub struct AnswerKey {
+    answers: Vec<Answer>,
+    // further accepted answers for each question, empty for single-answer keys
+    also_accepted: Vec<Vec<Answer>>,
+}
+
+impl AnswerKey {
+    /// A key where each question accepts any answer in its set. Empty sets are
+    /// not allowed.
+    pub fn with_alternatives(accepted: Vec<Vec<Answer>>) -> AnswerKey {
+        let mut answers = Vec::with_capacity(accepted.len());
+        let mut also_accepted = Vec::with_capacity(accepted.len());
+        for mut set in accepted {
+            set.sort_unstable();
+            set.dedup();
+            if set.is_empty() {
+                panic!("Every question needs an accepted answer!");
+            }
+            answers.push(set.remove(0));
+            also_accepted.push(set);
+        }
+        if also_accepted.iter().all(Vec::is_empty) {
+            also_accepted.clear();
+        }
+        AnswerKey {
+            answers,
+            also_accepted,
+        }
+    }
+
+    /// The first accepted answer for each question.
+    pub fn answers(&self) -> &[Answer] {
+        &self.answers
+    }
+
+    /// Every answer accepted for question `q`.
+    pub fn accepted(&self, q: usize) -> Vec<Answer> {
+        let mut accepted = vec![self.answers[q]];
+        if let Some(also) = self.also_accepted.get(q) {
+            accepted.extend(also);
+        }
+        accepted
+    }
+
+    pub fn accepts(&self, q: usize, answer: Answer) -> bool {
+        self.answers[q] == answer
+            || self
+                .also_accepted
+                .get(q)
+                .is_some_and(|also| also.contains(&answer))
+    }
+
+    /// Whether some question accepts more than one answer.
+    pub fn is_multi_answer(&self) -> bool {
+        !self.also_accepted.is_empty()
+    }
+
+    /// The key as letters, with questions that accept several answers written
+    /// as a set, e.g. `A{B,C}D`.
+    pub fn as_string(&self) -> String {
+        (0..self.answers.len())
+            .map(|q| self.question_string(q))
+            .collect()
+    }
+
+    // `A`, or `{B,C}` when several answers are accepted
+    fn question_string(&self, q: usize) -> String {
+        match self.also_accepted.get(q) {
+            Some(also) if !also.is_empty() => {
+                format!("{{{}}}", self.accepted(q).iter().join(","))
+            }
+            _ => self.answers[q].to_string(),
+        }
+    }
+
+    /// Every key at Hamming distance exactly `distance` from `base`, where
+    /// each differing question holds one of `choices` other than its answer
+    /// in `base`.
+    pub fn within_hamming(
+        base: &[Answer],
+        distance: usize,
+        choices: &[Answer],
+    ) -> impl Iterator<Item = AnswerKey> {
+        let base = base.to_vec();
+        let choices = Choices::uniform(choices, base.len());
+        (0..base.len())
+            .combinations(distance)
+            .flat_map(move |positions| keys_differing_at(&base, &choices, positions))
+    }
+
+    /// Whether the keys agree wherever neither has `X`, which stands for any
+    /// answer here, as in a partial official key. Note that scoring treats
+    /// `X` differently: a blank in an attempt is never correct, and an `X`
+    /// in a key accepts nothing.
+    pub fn matches(&self, other: &AnswerKey) -> bool {
+        self.answers.len() == other.answers.len()
+            && (0..self.answers.len()).all(|q| {
+                self.answers[q] == Answer::X
+                    || other.answers[q] == Answer::X
+                    || self.accepted(q) == other.accepted(q)
+            })
+    }
+
+    /// The 0-based questions where the keys accept different answers.
+    /// Panics if the keys have different lengths.
+    pub fn diff(&self, other: &AnswerKey) -> Vec<usize> {
+        if self.answers.len() != other.answers.len() {
+            panic!("Unmatched lengths!");
+        }
+        (0..self.answers.len())
+            .filter(|&q| self.accepted(q) != other.accepted(q))
+            .collect()
+    }
+
+    /// The differences from [`AnswerKey::diff`] as `Q3: A vs C`, one per
+    /// line, numbering questions from 1.
+    pub fn diff_display(&self, other: &AnswerKey) -> String {
+        self.diff(other)
+            .into_iter()
+            .map(|q| {
+                format!(
+                    "Q{}: {} vs {}",
+                    q + 1,
+                    self.question_string(q),
+                    other.question_string(q)
+                )
+            })
+            .join("\n")
+    }
+
+    /// Whether this key gives every attempt its recorded score.
+    pub fn consistent_with(&self, attempts: &[QuizAttempt]) -> bool {
+        attempts.iter().all(|att| att.check(self))
+    }
+}
+
+pub struct AnswerKeySet {
+    keys: Vec<AnswerKey>,
+}
+
+/// Number of candidate keys before and after reducing by one attempt.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ReduceStats {
+    pub before: usize,
+    pub after: usize,
+}
+
+impl ReduceStats {
+    pub fn eliminated(&self) -> usize {
+        self.before - self.after
+    }
+}
+
+/// The answer to each question every candidate key agrees on, and the
+/// answers still possible for the others, by 0-based question.
+#[derive(PartialEq, Eq, Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct AmbiguityReport {
+    pub resolved: BTreeMap<usize, Answer>,
+    pub ambiguous: BTreeMap<usize, Vec<Answer>>,
+}
+
+impl AmbiguityReport {
+    /// Write `{ "resolved": {...}, "ambiguous": {...} }` to `filename`.
+    #[cfg(feature = "serde")]
+    pub fn save_to_json(&self, filename: &str) -> io::Result<()> {
+        let mut f = BufWriter::new(create_output(filename)?);
+
+        serde_json::to_writer_pretty(&mut f, self)?;
+        writeln!(f)?;
+        f.flush()
+    }
+}
+
+impl From<Vec<Answer>> for AnswerKey {
+    fn from(value: Vec<Answer>) -> AnswerKey {
+        AnswerKey {
+            answers: value,
+            also_accepted: vec![],
+        }
+    }
+}
+
+impl FromStr for AnswerKey {
+    type Err = ParseError;
+
+    /// Parses letters, with `{B,C}` for a question that accepts several.
+    /// Spaces and dashes between letters are ignored.
+    fn from_str(s: &str) -> Result<AnswerKey, ParseError> {
+        let mut accepted = vec![];
+        let mut set: Option<Vec<Answer>> = None;
+        for c in s.to_uppercase().chars().filter(|&c| !is_separator(c)) {
+            match (c, set.as_mut()) {
+                ('{', None) => set = Some(vec![]),
+                ('}', Some(_)) => match set.take() {
+                    Some(answers) if !answers.is_empty() => accepted.push(answers),
+                    _ => return Err(ParseError::InvalidLetter(c)),
+                },
+                (',', Some(_)) => {}
+                (_, Some(answers)) => answers.push(Answer::try_from(c)?),
+                (_, None) => accepted.push(vec![Answer::try_from(c)?]),
+            }
+        }
+        if set.is_some() {
+            return Err(ParseError::InvalidLetter('{'));
+        }
+        Ok(AnswerKey::with_alternatives(accepted))
+    }
+}
+
+/// Reads one key per line as written by [`AnswerKeySet::save_to_file`],
+/// skipping blank lines and `#` comments.
+impl FromStr for AnswerKeySet {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<AnswerKeySet, ParseError> {
+        s.lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(str::parse)
+            .collect()
+    }
+}
+
+impl From<Vec<AnswerKey>> for AnswerKeySet {
+    fn from(value: Vec<AnswerKey>) -> AnswerKeySet {
+        AnswerKeySet { keys: value }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for AnswerKey {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.as_string())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for AnswerKeySet {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(serde::Serialize)]
+        struct Output<'a> {
+            count: usize,
+            keys: &'a [AnswerKey],
+            distribution: Vec<BTreeMap<Answer, f64>>,
+        }
+
+        Output {
+            count: self.keys.len(),
+            keys: &self.keys,
+            distribution: self.question_distribution(),
+        }
+        .serialize(serializer)
+    }
+}
+
+/// One key per line.
+impl fmt::Display for AnswerKeySet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, key) in self.keys.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", key.as_string())?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for AnswerKeySet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AnswerKeySet")
+            .field("count", &self.keys.len())
+            .finish()
+    }
+}
+
+impl FromIterator<AnswerKey> for AnswerKeySet {
+    fn from_iter<I: IntoIterator<Item = AnswerKey>>(iter: I) -> AnswerKeySet {
+        AnswerKeySet {
+            keys: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl IntoIterator for AnswerKeySet {
+    type Item = AnswerKey;
+    type IntoIter = std::vec::IntoIter<AnswerKey>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.keys.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a AnswerKeySet {
+    type Item = &'a AnswerKey;
+    type IntoIter = std::slice::Iter<'a, AnswerKey>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.keys.iter()
+    }
+}
+
+impl AnswerKeySet {
+    pub fn keys(&self) -> &[AnswerKey] {
+        &self.keys
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, AnswerKey> {
+        self.keys.iter()
+    }
+
+    /// Each key as written by [`AnswerKey::as_string`], in the set's order.
+    pub fn to_strings(&self) -> Vec<String> {
+        self.keys.iter().map(AnswerKey::as_string).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// The same keys in lexicographic order (`A < B < ... < X` per question).
+    pub fn sorted(mut self) -> AnswerKeySet {
+        self.keys.sort_unstable();
+        self
+    }
+
+    /// The same keys in lexicographic order with answers ranked as in
+    /// `order`, and any not in it after those in their usual order. This
+    /// only changes how keys are listed, answers still compare as usual.
+    pub fn sorted_by_order(mut self, order: &[Answer]) -> AnswerKeySet {
+        self.keys.sort_unstable_by(|a, b| cmp_in_order(a, b, order));
+        self
+    }
+
+    /// Keys present in both sets, in the order of `self`.
+    pub fn intersect(&self, other: &AnswerKeySet) -> AnswerKeySet {
+        let other: HashSet<&AnswerKey> = other.keys.iter().collect();
+        self.keys
+            .iter()
+            .filter(|key| other.contains(key))
+            .cloned()
+            .collect()
+    }
+
+    /// Keys present in either set without duplicates, those of `self` first.
+    pub fn union(&self, other: &AnswerKeySet) -> AnswerKeySet {
+        let mut seen = HashSet::new();
+        self.keys
+            .iter()
+            .chain(&other.keys)
+            .filter(|key| seen.insert(*key))
+            .cloned()
+            .collect()
+    }
+
+    /// Keep the keys consistent with `attempt`, in their current order.
+    pub fn reduce(mut self, attempt: &QuizAttempt) -> AnswerKeySet {
+        self.apply(attempt);
+        self
+    }
+
+    /// Like [`AnswerKeySet::reduce`] in place, returning how many keys are
+    /// left, for updating a count as attempts come in.
+    pub fn apply(&mut self, attempt: &QuizAttempt) -> usize {
+        #[cfg(not(feature = "rayon"))]
+        self.keys.retain(|k| attempt.check(k));
+        // collecting a parallel iterator over a Vec keeps the original order
+        #[cfg(feature = "rayon")]
+        {
+            self.keys = std::mem::take(&mut self.keys)
+                .into_par_iter()
+                .filter(|k| attempt.check(k))
+                .collect();
+        }
+
+        self.keys.len()
+    }
+
+    /// The keys consistent with all `attempts`, in their current order,
+    /// leaving this set as it is to reduce again by other attempts.
+    pub fn reduced_by(&self, attempts: &[QuizAttempt]) -> AnswerKeySet {
+        AnswerKeySet::from(
+            self.keys
+                .iter()
+                .filter(|key| key.consistent_with(attempts))
+                .cloned()
+                .collect::<Vec<AnswerKey>>(),
+        )
+    }
+
+    pub fn reduce_with_stats(self, attempt: &QuizAttempt) -> (AnswerKeySet, ReduceStats) {
+        let before = self.keys.len();
+        let reduced = self.reduce(attempt);
+        let after = reduced.keys.len();
+
+        (reduced, ReduceStats { before, after })
+    }
+
+    /// Reduce by each attempt in turn, failing with the index of the first
+    /// attempt that leaves no candidate keys.
+    pub fn reduce_checked(self, attempts: &[QuizAttempt]) -> Result<AnswerKeySet, SolveError> {
+        attempts
+            .iter()
+            .enumerate()
+            .try_fold(self, |ans_set, (i, att)| {
+                let ans_set = ans_set.reduce(att);
+                if ans_set.keys.is_empty() {
+                    Err(SolveError::NoConsistentKey { attempt: i })
+                } else {
+                    Ok(ans_set)
+                }
+            })
+    }
+
+    /// Reduce by each attempt read from `reader`, one line at a time, without
+    /// holding all of them in memory. Lines are read as by
+    /// [`LoadOptions::parse_line`], skipping blank lines and comments.
+    ///
+    /// The candidates have to be generated before the attempts are read,
+    /// usually from the highest-scoring attempt, so huge inputs take a first
+    /// pass to find that seed or are given one separately. Reducing by the
+    /// seed again when it comes up does no harm.
+    pub fn reduce_from_reader<R: BufRead>(
+        self,
+        reader: R,
+        options: &LoadOptions,
+    ) -> Result<AnswerKeySet, LoadError> {
+        let len = self.keys.first().map(|key| key.answers.len());
+        let mut ans_set = self;
+        let mut errors = vec![];
+        let mut mismatched = vec![];
+
+        for (i, line) in reader.lines().enumerate() {
+            let line = crate::input::clean_line(i, &line?).to_string();
+            if options.skips(&line) {
+                continue;
+            }
+            // short attempts are padded up to the candidates
+            let parsed = match (options.length, len) {
+                (None, Some(len)) => options.parse_fields(&line, len).0,
+                _ => options.parse_line(&line),
+            };
+            match parsed {
+                Ok(att) if len.is_some_and(|len| att.answers().len() != len) => {
+                    mismatched.push((i + 1, att.answers().len()));
+                }
+                Ok(att) => ans_set = ans_set.reduce(&att),
+                Err(error) => errors.push(LineError {
+                    line: i + 1,
+                    content: line,
+                    error,
+                }),
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err(LoadError::Parse(errors));
+        }
+        if let (Some(expected), false) = (len, mismatched.is_empty()) {
+            let mut lengths: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+            for (line, len) in mismatched {
+                lengths.entry(len).or_default().push(line);
+            }
+            return Err(LoadError::LengthMismatch(LengthMismatch {
+                expected,
+                lengths: lengths.into_iter().collect(),
+            }));
+        }
+        Ok(ans_set)
+    }
+
+    /// Reduce by attempts in order until at most one candidate is left,
+    /// returning the reduced set and how many attempts were applied. The
+    /// attempts after that point are only checked against the key left. If
+    /// an attempt leaves no key, or contradicts the one left, its index is
+    /// returned as [`SolveError::NoConsistentKey`].
+    pub fn reduce_until_unique(
+        self,
+        attempts: &[QuizAttempt],
+    ) -> Result<(AnswerKeySet, usize), SolveError> {
+        let (ans_set, stats) = self.reduce_until_unique_with_stats(attempts)?;
+        Ok((ans_set, stats.len()))
+    }
+
+    /// Like [`AnswerKeySet::reduce_until_unique`], recording the effect of
+    /// each attempt that was applied.
+    pub fn reduce_until_unique_with_stats(
+        self,
+        attempts: &[QuizAttempt],
+    ) -> Result<(AnswerKeySet, Vec<ReduceStats>), SolveError> {
+        self.reduce_until_unique_with_progress(attempts, || ())
+    }
+
+    /// Like [`AnswerKeySet::reduce_until_unique_with_stats`], calling
+    /// `on_step` once for each attempt applied.
+    pub fn reduce_until_unique_with_progress<F: Fn()>(
+        self,
+        attempts: &[QuizAttempt],
+        on_step: F,
+    ) -> Result<(AnswerKeySet, Vec<ReduceStats>), SolveError> {
+        let mut ans_set = self;
+        let mut stats = vec![];
+
+        for (attempt, att) in attempts.iter().enumerate() {
+            if ans_set.keys.len() <= 1 {
+                break;
+            }
+            let (reduced, step) = ans_set.reduce_with_stats(att);
+            ans_set = reduced;
+            stats.push(step);
+            on_step();
+            if ans_set.is_empty() {
+                return Err(SolveError::NoConsistentKey { attempt });
+            }
+        }
+        if ans_set.keys.len() == 1 {
+            let rest = &attempts[stats.len()..];
+            if let Some(i) = rest.iter().position(|att| !att.check(&ans_set.keys[0])) {
+                return Err(SolveError::NoConsistentKey {
+                    attempt: stats.len() + i,
+                });
+            }
+        }
+
+        Ok((ans_set, stats))
+    }
+
+    /// How many of the keys `seed` allows with the default choices are
+    /// consistent with all `attempts`, counting them as they are generated
+    /// instead of collecting them.
+    pub fn count_consistent(
+        seed: &QuizAttempt,
+        attempts: &[QuizAttempt],
+    ) -> Result<usize, SolveError> {
+        let choices = Choices::uniform(&DEFAULT_CHOICES, seed.answers().len());
+        AnswerKeySet::count_consistent_with(seed, attempts, &choices)
+    }
+
+    /// Like [`AnswerKeySet::count_consistent`] for any `choices`.
+    pub fn count_consistent_with(
+        seed: &QuizAttempt,
+        attempts: &[QuizAttempt],
+        choices: &Choices,
+    ) -> Result<usize, SolveError> {
+        Ok(seed
+            .valid_keys_iter(choices)?
+            .filter(|key| key.consistent_with(attempts))
+            .count())
+    }
+
+    /// Indices of a few `attempts` that, with the keys `seed` allows under
+    /// `choices`, narrow the candidates down as far as all of them do. Each
+    /// step greedily takes the attempt leaving the fewest keys, so the subset
+    /// is small but not guaranteed to be the smallest.
+    pub fn minimal_determining_subset(
+        seed: &QuizAttempt,
+        attempts: &[QuizAttempt],
+        choices: &Choices,
+    ) -> Result<Vec<usize>, SolveError> {
+        let mut set = seed.generate_valid_set(choices)?;
+        let mut chosen = vec![];
+
+        while set.len() > 1 {
+            let best = (0..attempts.len())
+                .filter(|i| !chosen.contains(i))
+                .map(|i| (set.iter().filter(|k| attempts[i].check(k)).count(), i))
+                .min();
+            match best {
+                Some((left, i)) if left < set.len() => {
+                    set = set.reduce(&attempts[i]);
+                    chosen.push(i);
+                }
+                _ => break,
+            }
+        }
+        Ok(chosen)
+    }
+
+    // for each question, how many candidate keys give each answer
+    fn answer_counts(&self) -> Vec<BTreeMap<Answer, usize>> {
+        let len = match self.keys.first() {
+            Some(key) => key.answers.len(),
+            None => return vec![],
+        };
+
+        let mut counts: Vec<BTreeMap<Answer, usize>> = vec![BTreeMap::new(); len];
+        for key in &self.keys {
+            for (q, &ans) in key.answers.iter().enumerate() {
+                *counts[q].entry(ans).or_insert(0) += 1;
+            }
+        }
+        counts
+    }
+
+    /// For each question, the fraction of candidate keys giving each answer.
+    pub fn question_distribution(&self) -> Vec<BTreeMap<Answer, f64>> {
+        self.answer_counts()
+            .into_iter()
+            .map(|count| {
+                count
+                    .into_iter()
+                    .map(|(ans, n)| (ans, n as f64 / self.keys.len() as f64))
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// The key made of the most common answer to each question, preferring
+    /// the earlier letter on ties. `None` if there are no candidates.
+    pub fn most_likely_key(&self) -> Option<AnswerKey> {
+        if self.keys.is_empty() {
+            return None;
+        }
+
+        Some(AnswerKey::from(
+            self.question_distribution()
+                .iter()
+                .map(|dist| {
+                    dist.iter()
+                        .fold(None, |best: Option<(Answer, f64)>, (&ans, &p)| match best {
+                            Some((_, best_p)) if best_p >= p => best,
+                            _ => Some((ans, p)),
+                        })
+                        .map(|(ans, _)| ans)
+                        .expect("Every question has at least one answer")
+                })
+                .collect::<Vec<Answer>>(),
+        ))
+    }
+
+    /// The keys from most to least plausible, scoring each by the sum over
+    /// questions of how often its answer appears among the candidates. Ties
+    /// are broken lexicographically, earlier keys first.
+    pub fn ranked(&self) -> Vec<&AnswerKey> {
+        self.ranked_with_support()
+            .into_iter()
+            .map(|(_, key)| key)
+            .collect()
+    }
+
+    // ranked keys along with their support
+    fn ranked_with_support(&self) -> Vec<(usize, &AnswerKey)> {
+        let counts = self.answer_counts();
+        let support = |key: &AnswerKey| -> usize {
+            key.answers
+                .iter()
+                .enumerate()
+                .map(|(q, ans)| counts[q][ans])
+                .sum()
+        };
+
+        let mut ranked: Vec<(usize, &AnswerKey)> =
+            self.keys.iter().map(|key| (support(key), key)).collect();
+        ranked.sort_unstable_by(|(a, key_a), (b, key_b)| b.cmp(a).then(key_a.cmp(key_b)));
+        ranked
+    }
+
+    /// The `n` most plausible keys as ranked by [`AnswerKeySet::ranked`].
+    pub fn top_n(&self, n: usize) -> AnswerKeySet {
+        self.ranked().into_iter().take(n).cloned().collect()
+    }
+
+    /// For each question, the answer every candidate key agrees on, or `None`
+    /// where they differ.
+    pub fn resolved_questions(&self) -> Vec<Option<Answer>> {
+        let first = match self.keys.first() {
+            Some(key) => key,
+            None => return vec![],
+        };
+
+        (0..first.answers.len())
+            .map(|q| {
+                let accepted = first.accepted(q);
+                if accepted.len() == 1 && self.keys.iter().all(|k| k.accepted(q) == accepted) {
+                    Some(accepted[0])
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Sort each question into resolved or ambiguous, see
+    /// [`AnswerKeySet::resolved_questions`].
+    pub fn ambiguity_report(&self) -> AmbiguityReport {
+        let mut report = AmbiguityReport::default();
+        for (q, resolved) in self.resolved_questions().into_iter().enumerate() {
+            match resolved {
+                Some(ans) => {
+                    report.resolved.insert(q, ans);
+                }
+                None => {
+                    let options = self
+                        .keys
+                        .iter()
+                        .flat_map(|k| k.accepted(q))
+                        .sorted()
+                        .dedup()
+                        .collect();
+                    report.ambiguous.insert(q, options);
+                }
+            }
+        }
+        report
+    }
+
+    /// Read keys written by [`AnswerKeySet::save_to_file`], skipping blank
+    /// and `#` lines.
+    pub fn load_from_file(filename: &str) -> Result<AnswerKeySet, LoadError> {
+        AnswerKeySet::load_from_reader(BufReader::new(File::open(filename)?))
+    }
+
+    /// Like [`AnswerKeySet::load_from_file`], reporting every line that is not
+    /// a key.
+    pub fn load_from_reader<R: BufRead>(reader: R) -> Result<AnswerKeySet, LoadError> {
+        let mut keys = vec![];
+        let mut errors = vec![];
+        for (i, line) in reader.lines().enumerate() {
+            let line = crate::input::clean_line(i, &line?).to_string();
+            if line.trim().is_empty() || line.trim().starts_with('#') {
+                continue;
+            }
+            match line.parse() {
+                Ok(key) => keys.push(key),
+                Err(error) => errors.push(LineError {
+                    line: i + 1,
+                    content: line,
+                    error,
+                }),
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err(LoadError::Parse(errors));
+        }
+        Ok(AnswerKeySet::from(keys))
+    }
+
+    pub fn save_to_file(&self, filename: &str) -> io::Result<()> {
+        self.save_to_file_with_header(filename, &[])
+    }
+
+    /// Like [`AnswerKeySet::save_to_file`], starting the file with `header`
+    /// as `#` comment lines.
+    pub fn save_to_file_with_header(&self, filename: &str, header: &[String]) -> io::Result<()> {
+        self.save_to_file_in_order(filename, header, &[])
+    }
+
+    /// Like [`AnswerKeySet::save_to_file_with_header`], listing the keys as
+    /// [`AnswerKeySet::sorted_by_order`] does.
+    pub fn save_to_file_in_order(
+        &self,
+        filename: &str,
+        header: &[String],
+        order: &[Answer],
+    ) -> io::Result<()> {
+        self.write_in_order(BufWriter::new(create_output(filename)?), header, order)
+    }
+
+    /// Write `header` as `#` comment lines, then one key per line in sorted
+    /// order.
+    pub fn write_with_header<W: Write>(&self, w: W, header: &[String]) -> io::Result<()> {
+        self.write_in_order(w, header, &[])
+    }
+
+    /// Like [`AnswerKeySet::write_with_header`], listing the keys as
+    /// [`AnswerKeySet::sorted_by_order`] does.
+    pub fn write_in_order<W: Write>(
+        &self,
+        mut w: W,
+        header: &[String],
+        order: &[Answer],
+    ) -> io::Result<()> {
+        for line in header {
+            writeln!(w, "# {}", line)?;
+        }
+
+        // sorted here too so the file does not depend on how the set was built
+        let mut keys: Vec<&AnswerKey> = self.keys.iter().collect();
+        keys.sort_unstable_by(|a, b| cmp_in_order(a, b, order));
+        for key in keys {
+            writeln!(w, "{}", key.as_string())?;
+        }
+        w.flush()
+    }
+
+    /// Write `rank,key,agreement_score` rows to `filename`, see
+    /// [`AnswerKeySet::write_csv`].
+    pub fn save_to_csv(&self, filename: &str) -> io::Result<()> {
+        self.write_csv(BufWriter::new(create_output(filename)?))
+    }
+
+    /// Write the keys in [`AnswerKeySet::ranked`] order as CSV rows. The
+    /// agreement score is the average over questions of the fraction of
+    /// candidates sharing the key's answer, and tied keys share a rank.
+    pub fn write_csv<W: Write>(&self, mut w: W) -> io::Result<()> {
+        writeln!(w, "rank,key,agreement_score")?;
+        let questions = self.keys.first().map_or(0, |key| key.answers.len());
+        let total = (self.keys.len() * questions) as f64;
+
+        let ranked = self.ranked_with_support();
+        let mut rank = 0;
+        for (i, &(support, key)) in ranked.iter().enumerate() {
+            if i == 0 || ranked[i - 1].0 != support {
+                rank = i + 1;
+            }
+            let key = key.as_string();
+            // multi-answer keys have commas in them
+            let key = if key.contains(',') {
+                format!("\"{}\"", key)
+            } else {
+                key
+            };
+            writeln!(w, "{},{},{:.4}", rank, key, support as f64 / total)?;
+        }
+        w.flush()
+    }
+
+    /// Write `{ "count": N, "keys": [...], "distribution": [...] }` to `filename`.
+    #[cfg(feature = "serde")]
+    pub fn save_to_json(&self, filename: &str) -> io::Result<()> {
+        let mut f = BufWriter::new(create_output(filename)?);
+
+        serde_json::to_writer_pretty(&mut f, self)?;
+        writeln!(f)?;
+        f.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        extract_attempts_from_reader, Answer, AnswerKey, AnswerKeySet, Choices, LoadError,
+        LoadOptions, ParseError, QuizAttempt, ReduceStats, SolveError, DEFAULT_CHOICES,
+    };
+
+    #[test]
+    fn key_round_trips_through_string() {
+        let key: AnswerKey = "abCD\n".parse().unwrap();
+
+        assert_eq!(
+            key,
+            AnswerKey::from(vec![Answer::A, Answer::B, Answer::C, Answer::D])
+        );
+        assert_eq!(key.as_string().parse::<AnswerKey>(), Ok(key));
+        assert_eq!(
+            "AB?D".parse::<AnswerKey>(),
+            Err(ParseError::InvalidLetter('?'))
+        );
+
+        let multi: AnswerKey = "a{c,b}{D}".parse().unwrap();
+        assert_eq!(multi.as_string(), "A{B,C}D");
+        assert_eq!(multi.accepted(1), vec![Answer::B, Answer::C]);
+        assert_eq!(multi.as_string().parse::<AnswerKey>(), Ok(multi));
+        assert!("A{B".parse::<AnswerKey>().is_err());
+        assert_eq!(
+            "a b-{c, d}".parse::<AnswerKey>().unwrap().as_string(),
+            "AB{C,D}"
+        );
+        assert!("A{}D".parse::<AnswerKey>().is_err());
+    }
+
+    #[test]
+    fn set_formatting() {
+        let set = AnswerKeySet::from(vec![
+            AnswerKey::from(vec![Answer::A, Answer::B]),
+            "A{B,C}".parse().unwrap(),
+        ]);
+
+        assert_eq!(set.to_string(), "AB\nA{B,C}");
+        assert_eq!(format!("{:?}", set), "AnswerKeySet { count: 2 }");
+        assert_eq!(AnswerKeySet::from(vec![]).to_string(), "");
+    }
+
+    #[test]
+    fn set_iteration() {
+        let keys = vec![
+            AnswerKey::from(vec![Answer::A, Answer::B]),
+            AnswerKey::from(vec![Answer::C, Answer::D]),
+        ];
+        let set = AnswerKeySet::from(keys.clone());
+
+        assert_eq!(set.len(), 2);
+        assert!(!set.is_empty());
+        assert!(AnswerKeySet::from(vec![]).is_empty());
+        assert_eq!(
+            set.iter().collect::<Vec<_>>(),
+            keys.iter().collect::<Vec<_>>()
+        );
+        assert_eq!((&set).into_iter().count(), 2);
+        assert_eq!(
+            set.to_strings(),
+            keys.iter().map(|k| k.as_string()).collect::<Vec<_>>()
+        );
+        assert_eq!(set.into_iter().collect::<Vec<_>>(), keys);
+    }
+
+    #[test]
+    fn intersect_and_union() {
+        let set = |keys: &[&str]| -> AnswerKeySet {
+            keys.iter()
+                .map(|k| k.parse::<AnswerKey>().unwrap())
+                .collect()
+        };
+        let left = set(&["AB", "AC", "BD"]);
+        let right = set(&["BD", "CC", "AB", "BD"]);
+
+        assert_eq!(left.intersect(&right).to_string(), "AB\nBD");
+        assert_eq!(left.union(&right).to_string(), "AB\nAC\nBD\nCC");
+        assert!(left.intersect(&AnswerKeySet::from(vec![])).is_empty());
+    }
+
+    #[test]
+    fn sorted_is_lexicographic() {
+        let set: AnswerKeySet = ["BA", "AD", "AB", "XA"]
+            .iter()
+            .map(|k| k.parse::<AnswerKey>().unwrap())
+            .collect();
+
+        assert_eq!(set.sorted().to_string(), "AB\nAD\nBA\nXA");
+    }
+
+    #[test]
+    fn output_round_trips_with_header() {
+        let set: AnswerKeySet = "CD\nAB\nA{B,C}".parse().unwrap();
+        let mut out = vec![];
+        set.write_with_header(&mut out, &["2 attempts".to_string()])
+            .unwrap();
+        let out = String::from_utf8(out).unwrap();
+
+        assert_eq!(out, "# 2 attempts\nAB\nA{B,C}\nCD\n");
+        assert_eq!(out.parse::<AnswerKeySet>().unwrap().len(), 3);
+        assert!("AB\n%%".parse::<AnswerKeySet>().is_err());
+
+        let loaded = AnswerKeySet::load_from_reader(out.as_bytes()).unwrap();
+        assert_eq!(loaded.to_string(), "AB\nA{B,C}\nCD");
+        match AnswerKeySet::load_from_reader("AB\n%%\nCD\n".as_bytes()) {
+            Err(LoadError::Parse(errors)) => assert_eq!(errors[0].line, 2),
+            other => panic!("Expected a parse error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn saves_into_missing_directories() {
+        let dir = std::env::temp_dir().join(format!("keys-{}", std::process::id()));
+        let path = dir.join("nested").join("keys.txt");
+        let set: AnswerKeySet = "AB\nCD".parse().unwrap();
+
+        set.save_to_file(path.to_str().unwrap()).unwrap();
+        let loaded = AnswerKeySet::load_from_file(path.to_str().unwrap());
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(loaded.unwrap().keys(), set.keys());
+    }
+
+    #[test]
+    fn sorts_in_a_custom_order() {
+        use crate::Answer::*;
+
+        let set: AnswerKeySet = "AB\nXA\nBX\nAX".parse().unwrap();
+        assert_eq!(
+            set.sorted_by_order(&[X, B]).to_strings(),
+            vec!["XA", "BX", "AX", "AB"]
+        );
+
+        let set: AnswerKeySet = "AB\nXA\nBX\nAX".parse().unwrap();
+        let mut out = vec![];
+        set.write_in_order(&mut out, &[], &[X, A, B]).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "XA\nAX\nAB\nBX\n");
+    }
+
+    #[test]
+    fn csv_ranks_by_agreement() {
+        let set: AnswerKeySet = "CA\nAB\nBA\nAA\nA{B,C}".parse().unwrap();
+        let mut out = vec![];
+        set.write_csv(&mut out).unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "rank,key,agreement_score\n\
+             1,AA,0.6000\n\
+             2,AB,0.5000\n\
+             2,\"A{B,C}\",0.5000\n\
+             4,BA,0.4000\n\
+             4,CA,0.4000\n"
+        );
+    }
+
+    #[test]
+    fn diffs_keys() {
+        let key: AnswerKey = "ABCD".parse().unwrap();
+        let other: AnswerKey = "ABAC".parse().unwrap();
+
+        assert_eq!(key.diff(&other), vec![2, 3]);
+        assert_eq!(key.diff_display(&other), "Q3: C vs A\nQ4: D vs C");
+        assert!(key.diff(&key).is_empty());
+        let multi: AnswerKey = "A{B,C}CD".parse().unwrap();
+        assert_eq!(key.diff_display(&multi), "Q2: B vs {B,C}");
+    }
+
+    #[test]
+    fn enumerates_keys_within_hamming_distance() {
+        use crate::Answer::*;
+
+        let base = [A, B, C, D, A];
+        for distance in 0..=5 {
+            let keys: Vec<AnswerKey> =
+                AnswerKey::within_hamming(&base, distance, &DEFAULT_CHOICES).collect();
+            let combinations = (0..distance).fold(1, |c, i| c * (5 - i) / (i + 1));
+            assert_eq!(keys.len(), combinations * 3usize.pow(distance as u32));
+            assert!(keys.iter().all(|key| key
+                .answers()
+                .iter()
+                .zip(&base)
+                .filter(|(k, b)| k != b)
+                .count()
+                == distance));
+        }
+        assert_eq!(
+            AnswerKey::within_hamming(&base, 6, &DEFAULT_CHOICES).count(),
+            0
+        );
+    }
+
+    #[test]
+    fn matches_with_wildcards() {
+        let key: AnswerKey = "ABCD".parse().unwrap();
+
+        assert!(key.matches(&"AXCX".parse().unwrap()));
+        assert!("XBCD".parse::<AnswerKey>().unwrap().matches(&key));
+        assert!(!key.matches(&"AXCA".parse().unwrap()));
+        assert!(!key.matches(&"ABC".parse().unwrap()));
+        assert!(!key.matches(&"A{B,C}CD".parse().unwrap()));
+        // unlike a blank in an attempt, which never scores
+        assert_eq!(QuizAttempt::from_string("AXCX", 2.0).score_against(&key), 2);
+    }
+
+    #[test]
+    fn key_consistency() {
+        let key: AnswerKey = "AACD".parse().unwrap();
+        let attempts = vec![
+            QuizAttempt::from_string("ABCD", 3.0),
+            QuizAttempt::from_string("ABCA", 2.0),
+        ];
+
+        assert!(key.consistent_with(&attempts));
+        assert!(!"ABCD"
+            .parse::<AnswerKey>()
+            .unwrap()
+            .consistent_with(&attempts));
+    }
+
+    #[test]
+    fn reduce_keeps_order() {
+        let set: AnswerKeySet = "DD\nAB\nCB\nBA\nAD\nBB".parse().unwrap();
+        let reduced = set.reduce(&QuizAttempt::from_string("AB", 1.0));
+
+        assert_eq!(reduced.to_string(), "CB\nAD\nBB");
+    }
+
+    #[test]
+    fn applies_attempts_in_place() {
+        let mut set: AnswerKeySet = "DD\nAB\nCB\nBA\nAD\nBB".parse().unwrap();
+
+        assert_eq!(set.apply(&QuizAttempt::from_string("AB", 1.0)), 3);
+        assert_eq!(set.apply(&QuizAttempt::from_string("AD", 2.0)), 1);
+        assert_eq!(set.to_string(), "AD");
+        assert_eq!(set.apply(&QuizAttempt::from_string("CC", 2.0)), 0);
+    }
+
+    #[test]
+    fn reduces_by_different_subsets() {
+        let set: AnswerKeySet = "DD\nAB\nCB\nBA\nAD\nBB".parse().unwrap();
+        let attempts = [
+            QuizAttempt::from_string("AB", 1.0),
+            QuizAttempt::from_string("BD", 1.0),
+        ];
+
+        assert_eq!(set.reduced_by(&attempts[..1]).to_string(), "CB\nAD\nBB");
+        assert_eq!(set.reduced_by(&attempts[1..]).to_string(), "DD\nBA\nAD\nBB");
+        assert_eq!(set.reduced_by(&attempts).to_string(), "AD\nBB");
+        assert_eq!(set.len(), 6);
+    }
+
+    #[test]
+    fn reduce_checked_reports_contradiction() {
+        let seed = QuizAttempt::from_string("ABCD", 3.0);
+        let attempts = vec![
+            QuizAttempt::from_string("ABCA", 2.0),
+            QuizAttempt::from_string("ABCA", 3.0),
+            QuizAttempt::from_string("ABDD", 2.0),
+        ];
+
+        assert!(seed
+            .generate_valid_set(&Choices::uniform(&DEFAULT_CHOICES, 4))
+            .unwrap()
+            .reduce_checked(&attempts[..1])
+            .is_ok());
+        assert_eq!(
+            seed.generate_valid_set(&Choices::uniform(&DEFAULT_CHOICES, 4))
+                .unwrap()
+                .reduce_checked(&attempts)
+                .err(),
+            Some(SolveError::NoConsistentKey { attempt: 1 })
+        );
+    }
+
+    #[test]
+    fn reduce_stops_once_unique() {
+        let seed = QuizAttempt::from_string("ABCD", 3.0);
+        let attempts = vec![
+            QuizAttempt::from_string("ABCA", 2.0),
+            QuizAttempt::from_string("ACCD", 3.0),
+            QuizAttempt::from_string("DDDD", 1.0),
+            QuizAttempt::from_string("ABDD", 2.0),
+        ];
+
+        let (set, used) = seed
+            .generate_valid_set(&Choices::uniform(&DEFAULT_CHOICES, 4))
+            .unwrap()
+            .reduce_until_unique(&attempts)
+            .unwrap();
+        assert_eq!(set.keys().len(), 1);
+        assert_eq!(used, 3);
+
+        let (_, stats) = seed
+            .generate_valid_set(&Choices::uniform(&DEFAULT_CHOICES, 4))
+            .unwrap()
+            .reduce_until_unique_with_stats(&attempts)
+            .unwrap();
+        assert_eq!(
+            stats,
+            vec![
+                ReduceStats {
+                    before: 12,
+                    after: 9
+                },
+                ReduceStats {
+                    before: 9,
+                    after: 2
+                },
+                ReduceStats {
+                    before: 2,
+                    after: 1
+                },
+            ]
+        );
+        assert_eq!(stats[1].eliminated(), 7);
+
+        // an attempt after the key is pinned down can still rule it out
+        let mut contradicted = attempts.clone();
+        contradicted[3] = QuizAttempt::from_string("ABDD", 1.0);
+        let reduced = seed
+            .generate_valid_set(&Choices::uniform(&DEFAULT_CHOICES, 4))
+            .unwrap()
+            .reduce_until_unique(&contradicted);
+        assert_eq!(
+            reduced.err(),
+            Some(SolveError::NoConsistentKey { attempt: 3 })
+        );
+
+        // as can one that leaves no key at all
+        let reduced = seed
+            .generate_valid_set(&Choices::uniform(&DEFAULT_CHOICES, 4))
+            .unwrap()
+            .reduce_until_unique(&[QuizAttempt::from_string("ABCD", 0.0)]);
+        assert_eq!(
+            reduced.err(),
+            Some(SolveError::NoConsistentKey { attempt: 0 })
+        );
+    }
+
+    #[test]
+    fn distribution_and_most_likely_key() {
+        let set = AnswerKeySet::from(vec![
+            AnswerKey::from(vec![Answer::A, Answer::B]),
+            AnswerKey::from(vec![Answer::A, Answer::C]),
+            AnswerKey::from(vec![Answer::B, Answer::C]),
+            AnswerKey::from(vec![Answer::A, Answer::B]),
+        ]);
+
+        let dist = set.question_distribution();
+        assert_eq!(dist.len(), 2);
+        assert_eq!(dist[0][&Answer::A], 0.75);
+        assert_eq!(dist[0][&Answer::B], 0.25);
+        assert_eq!(dist[1][&Answer::B], 0.5);
+        assert_eq!(dist[1][&Answer::C], 0.5);
+
+        assert_eq!(
+            set.most_likely_key(),
+            Some(AnswerKey::from(vec![Answer::A, Answer::B]))
+        );
+        assert_eq!(AnswerKeySet::from(vec![]).most_likely_key(), None);
+    }
+
+    #[test]
+    fn ranks_by_plurality_support() {
+        let set: AnswerKeySet = ["BC", "AC", "AB", "BB", "AD"]
+            .iter()
+            .map(|k| k.parse::<AnswerKey>().unwrap())
+            .collect();
+
+        // A and B appear 3 and 2 times first, B and C twice second
+        let ranked: Vec<String> = set.ranked().iter().map(|k| k.as_string()).collect();
+        assert_eq!(ranked, vec!["AB", "AC", "AD", "BB", "BC"]);
+        assert_eq!(set.top_n(2).to_string(), "AB\nAC");
+        assert_eq!(set.top_n(10).len(), 5);
+    }
+
+    #[test]
+    fn resolved_questions() {
+        let set = AnswerKeySet::from(vec![
+            "ABC".parse::<AnswerKey>().unwrap(),
+            "ADC".parse().unwrap(),
+            "A{B,D}C".parse().unwrap(),
+        ]);
+
+        assert_eq!(
+            set.resolved_questions(),
+            vec![Some(Answer::A), None, Some(Answer::C)]
+        );
+        assert_eq!(
+            AnswerKeySet::from(vec!["A{B,C}".parse::<AnswerKey>().unwrap()]).resolved_questions(),
+            vec![Some(Answer::A), None]
+        );
+    }
+
+    #[test]
+    fn finds_a_small_determining_subset() {
+        let seed = QuizAttempt::from_string("ABCD", 2.0);
+        let attempts: Vec<QuizAttempt> = [
+            ("ABCD", 2.0),
+            ("BBCC", 1.0),
+            ("ACCD", 3.0),
+            ("DBCA", 1.0),
+            ("ACBD", 2.0),
+            ("ACCC", 2.0),
+        ]
+        .iter()
+        .map(|&(answers, score)| QuizAttempt::from_string(answers, score))
+        .collect();
+        let choices = Choices::uniform(&DEFAULT_CHOICES, 4);
+
+        let subset = AnswerKeySet::minimal_determining_subset(&seed, &attempts, &choices).unwrap();
+        let all = attempts
+            .iter()
+            .fold(seed.generate_valid_set(&choices).unwrap(), |set, att| {
+                set.reduce(att)
+            });
+        let picked = subset
+            .iter()
+            .fold(seed.generate_valid_set(&choices).unwrap(), |set, &i| {
+                set.reduce(&attempts[i])
+            });
+
+        assert_eq!(picked.keys(), all.keys());
+        assert!(subset.len() < attempts.len());
+        assert!(!subset.contains(&0));
+
+        assert_eq!(
+            AnswerKeySet::count_consistent(&seed, &attempts).unwrap(),
+            all.len()
+        );
+        let ab = Choices::uniform(&[Answer::A, Answer::B], 4);
+        assert_eq!(
+            AnswerKeySet::count_consistent_with(&seed, &attempts[..1], &ab).unwrap(),
+            seed.generate_valid_set(&ab).unwrap().len()
+        );
+    }
+
+    #[test]
+    fn reduces_from_a_reader() {
+        let choices = Choices::uniform(&DEFAULT_CHOICES, 4);
+        let seed = QuizAttempt::from_string("ABCD", 3.0);
+        let input = "# later attempts\nABCA,2\n\nAACD,2\n";
+
+        let streamed = seed
+            .generate_valid_set(&choices)
+            .unwrap()
+            .reduce_from_reader(input.as_bytes(), &LoadOptions::default())
+            .unwrap();
+        let loaded =
+            extract_attempts_from_reader(input.as_bytes(), &LoadOptions::default()).unwrap();
+        let reduced = loaded
+            .iter()
+            .fold(seed.generate_valid_set(&choices).unwrap(), |set, att| {
+                set.reduce(att)
+            });
+        assert_eq!(streamed.keys(), reduced.keys());
+
+        // as exported by spreadsheets
+        let exported = format!("\u{feff}{}", input.replace('\n', "\r\n"));
+        let cleaned = seed
+            .generate_valid_set(&choices)
+            .unwrap()
+            .reduce_from_reader(exported.as_bytes(), &LoadOptions::default())
+            .unwrap();
+        assert_eq!(cleaned.keys(), reduced.keys());
+        let saved = format!(
+            "\u{feff}{}",
+            "# candidates\nABCA\nABCB\n".replace('\n', "\r\n")
+        );
+        let loaded = AnswerKeySet::load_from_reader(saved.as_bytes()).unwrap();
+        assert_eq!(loaded.to_strings(), vec!["ABCA", "ABCB"]);
+
+        // padded up to the candidates, not to the line itself
+        let padding = LoadOptions {
+            pad_short: true,
+            ..LoadOptions::default()
+        };
+        let padded = seed
+            .generate_valid_set(&choices)
+            .unwrap()
+            .reduce_from_reader("AB,1\n".as_bytes(), &padding)
+            .unwrap();
+        let short = QuizAttempt::from_string("ABXX", 1.0);
+        assert_eq!(
+            padded.keys(),
+            seed.generate_valid_set(&choices)
+                .unwrap()
+                .reduce(&short)
+                .keys()
+        );
+
+        match seed
+            .generate_valid_set(&choices)
+            .unwrap()
+            .reduce_from_reader(
+                "ABCA,2\nABC,1\nAB%D,1\n".as_bytes(),
+                &LoadOptions::default(),
+            ) {
+            Err(LoadError::Parse(errors)) => assert_eq!(errors[0].line, 3),
+            other => panic!("Expected a parse error, got {:?}", other),
+        }
+        match seed
+            .generate_valid_set(&choices)
+            .unwrap()
+            .reduce_from_reader("ABCA,2\nABC,1\n".as_bytes(), &LoadOptions::default())
+        {
+            Err(LoadError::LengthMismatch(mismatch)) => {
+                assert_eq!(
+                    (mismatch.expected, mismatch.lengths),
+                    (4, vec![(3, vec![2])])
+                )
+            }
+            other => panic!("Expected a length mismatch, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn reports_ambiguity() {
+        let set: AnswerKeySet = "ABC\nADC\nA{B,C}C".parse().unwrap();
+        let report = set.ambiguity_report();
+
+        assert_eq!(
+            report.resolved,
+            [(0, Answer::A), (2, Answer::C)].into_iter().collect()
+        );
+        assert_eq!(
+            report.ambiguous,
+            [(1, vec![Answer::B, Answer::C, Answer::D])]
+                .into_iter()
+                .collect()
+        );
+        assert_eq!(
+            AnswerKeySet::from(vec![]).ambiguity_report(),
+            Default::default()
+        );
+
+        #[cfg(feature = "serde")]
+        assert_eq!(
+            serde_json::to_string(&report).unwrap(),
+            r#"{"resolved":{"0":"A","2":"C"},"ambiguous":{"1":["B","C","D"]}}"#
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serializes_to_json() {
+        let set = AnswerKeySet::from(vec![
+            AnswerKey::from(vec![Answer::A, Answer::B]),
+            AnswerKey::from(vec![Answer::A, Answer::C]),
+        ]);
+
+        assert_eq!(
+            serde_json::to_string(&set).unwrap(),
+            r#"{"count":2,"keys":["AB","AC"],"distribution":[{"A":1.0},{"B":0.5,"C":0.5}]}"#
+        );
+    }
+}
@@ -0,0 +1,28 @@
+mod answer;
+mod attempt;
+mod choices;
+mod error;
+#[cfg(feature = "rand")]
+mod generate;
+mod input;
+mod key;
+mod report;
+mod scoring;
+mod solve;
+
+pub use answer::{Answer, DEFAULT_CHOICES, TRUE_FALSE};
+pub use attempt::QuizAttempt;
+pub use choices::Choices;
+pub use error::{InvalidAnswerChar, LengthMismatch, LineError, LoadError, ParseError, SolveError};
+#[cfg(feature = "rand")]
+pub use generate::synthetic_quiz;
+pub use input::{
+    extract_attempts_from_file, extract_attempts_from_reader, LoadOptions, LoadedAttempts,
+};
+pub use key::{AmbiguityReport, AnswerKey, AnswerKeySet, ReduceStats};
+pub use report::{ReportedAttempt, SolveReport};
+pub use scoring::{Score, ScoringScheme, POINT_UNITS};
+pub use solve::{
+    infer_from_pairwise_diffs, narrow_choices, solve, solve_by_intersection, solve_constrained,
+    solve_with,
+};
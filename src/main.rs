@@ -1,344 +1,1374 @@
-use core::fmt;
-use std::env;
 use std::fs;
-use std::fs::File;
-use std::io::{stdin, BufWriter, Read, Write};
+use std::io::{self, stdin, stdout, BufRead, BufWriter, IsTerminal, Read, Write};
+use std::path::Path;
+use std::process;
+use std::time::Instant;
 
+use answerkey_search::{
+    narrow_choices, Answer, AnswerKey, AnswerKeySet, Choices, LoadOptions, LoadedAttempts,
+    QuizAttempt, ScoringScheme, SolveError, TRUE_FALSE,
+};
+use clap::error::ErrorKind;
+use clap::parser::ValueSource;
+use clap::{Args as _, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
 use itertools::Itertools;
+use log::{error, info, warn, Level, LevelFilter};
 
-#[derive(PartialOrd, Ord, Clone, Copy, PartialEq, Eq, Debug)]
-enum Answer {
-    A,
-    B,
-    C,
-    D,
-    X,
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum OutputFormat {
+    /// One key per line
+    Text,
+    /// A JSON object with the keys and per-question distribution
+    Json,
+    /// `rank,key,agreement_score` rows, most plausible keys first
+    Csv,
 }
 
-impl fmt::Display for Answer {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Answer::A => write!(f, "A"),
-            Answer::B => write!(f, "B"),
-            Answer::C => write!(f, "C"),
-            Answer::D => write!(f, "D"),
-            Answer::X => write!(f, "X"),
-        }
-    }
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum InputFormat {
+    /// `answers,score` lines
+    Text,
+    /// A JSON array of `{"answers": ..., "score": ...}` objects
+    Json,
+}
+
+/// Exit code when several candidate keys are left.
+const AMBIGUOUS: i32 = 2;
+/// Exit code when no key is consistent with the attempts, or the key given
+/// to `verify` is not.
+const CONTRADICTION: i32 = 3;
+
+#[derive(Parser, Debug)]
+#[command(
+    version,
+    about = "Search for answer keys consistent with scored quiz attempts",
+    after_help = "Exit status: 0 if the key was found, 1 on bad input, 2 if several keys are \
+                  possible and 3 if none is."
+)]
+struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    common: Common,
+
+    /// Used when no subcommand is given, see `parse_args`
+    #[command(flatten)]
+    search: SearchArgs,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Search for the answer keys consistent with the attempts (the default)
+    Search(SearchArgs),
+    /// Check a key against the attempts, exiting with 3 if it contradicts any
+    Verify {
+        /// The key to check, such as ABCD
+        #[arg(value_name = "KEYSTRING")]
+        key: String,
+
+        #[command(flatten)]
+        input: InputPath,
+    },
+    /// Print how often each answer was given to each question and how well
+    /// each question separates high from low scorers
+    Stats {
+        #[command(flatten)]
+        input: InputPath,
+    },
+    /// Only report the size of the search, without generating any keys
+    Estimate {
+        #[command(flatten)]
+        input: InputPath,
+    },
+    /// Make up a random answer key and attempts scored against it, to try
+    /// the search on
+    Generate(GenerateArgs),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Mode {
+    Search,
+    Verify(String),
+    Stats,
+    Estimate,
+}
+
+/// Options shared by every subcommand.
+#[derive(clap::Args, Debug)]
+struct Common {
+    /// File of `answers,score` (or `id,answers,score`) lines to read attempts
+    /// from, or `-` for stdin
+    #[arg(short, long, default_value = "attempts.txt", global = true)]
+    input: String,
+
+    /// Type attempts at a prompt instead of reading them from a file
+    #[arg(long, conflicts_with = "input", global = true)]
+    interactive: bool,
+
+    /// File of answers, one attempt per line, to read along with `--scores`
+    /// instead of `--input`
+    #[arg(
+        global = true,
+        long,
+        value_name = "FILE",
+        requires = "scores",
+        conflicts_with_all = ["input", "interactive", "input_format"]
+    )]
+    answers: Option<String>,
+
+    /// File of scores for the attempts in `--answers`, line by line
+    #[arg(long, value_name = "FILE", requires = "answers", global = true)]
+    scores: Option<String>,
+
+    /// Format of the input; `json` by default for `.json` files, else `text`
+    #[arg(long, value_enum, global = true)]
+    input_format: Option<InputFormat>,
+
+    /// Separator between answers and score; `\t` or `tab` for tabs
+    #[arg(short, long, default_value = ",", value_parser = parse_delimiter, global = true)]
+    delimiter: char,
+
+    /// Character marking an unanswered question in the input, such as `-`
+    /// or `.`
+    #[arg(long, value_name = "CHAR", default_value_t = 'X', value_parser = parse_blank_char, global = true)]
+    blank_char: char,
+
+    /// Lines of the input starting with this character are comments
+    #[arg(long, value_name = "CHAR", default_value_t = '#', global = true)]
+    comment_char: char,
+
+    /// Points for each correct answer
+    #[arg(
+        long,
+        default_value_t = 1.0,
+        allow_negative_numbers = true,
+        global = true
+    )]
+    correct_points: f64,
+
+    /// Points for each wrong answer, e.g. -0.25 for negative marking
+    #[arg(
+        long,
+        default_value_t = 0.0,
+        allow_negative_numbers = true,
+        global = true
+    )]
+    wrong_points: f64,
+
+    /// Points for each blank answer
+    #[arg(
+        long,
+        default_value_t = 0.0,
+        allow_negative_numbers = true,
+        global = true
+    )]
+    blank_points: f64,
+
+    /// Comma-separated point value of each question, e.g. 1,3,2
+    #[arg(long, value_name = "WEIGHTS", value_delimiter = ',', global = true)]
+    weights: Vec<i32>,
+
+    /// Accept keys under which an attempt gets up to T answers (or weight)
+    /// more or fewer right than its score says, for hand-tallied scores. A
+    /// slip then cannot rule out the true key, but more keys are left
+    #[arg(long, value_name = "T", default_value_t = 0, global = true)]
+    score_tolerance: usize,
+
+    /// Number of questions, to reject attempts with any other number of
+    /// answers instead of going by the attempts; needed by `generate`
+    #[arg(long, value_name = "L", global = true)]
+    length: Option<usize>,
+
+    /// Number of choices per question, lettered from A
+    #[arg(long, value_name = "N", default_value_t = 4, value_parser = clap::value_parser!(u8).range(2..=6), global = true)]
+    choices: u8,
+
+    /// Answers known to be correct, e.g. 1=A,5=C; questions are numbered
+    /// from 1, as everywhere else in the output, so 0 is rejected
+    #[arg(long, value_name = "ANSWERS", value_parser = parse_known, global = true)]
+    known: Option<Known>,
+
+    /// Questions answered T or F, e.g. 1,4-6 (numbered from 1)
+    #[arg(long, value_name = "QUESTIONS", value_parser = parse_questions, global = true)]
+    true_false: Option<Questions>,
+
+    /// Also search keys where up to N questions accept more than one answer
+    #[arg(long, value_name = "N", default_value_t = 0, global = true)]
+    multi_answer: usize,
+
+    /// Drop attempts that repeat an earlier line's answers and score
+    #[arg(long, global = true)]
+    dedup_attempts: bool,
+
+    /// Fill attempts shorter than the rest with blanks instead of rejecting
+    /// them, for students who stopped early
+    #[arg(long, global = true)]
+    pad_short: bool,
+
+    /// Exit without waiting for a key press at the end
+    #[arg(long, global = true)]
+    no_wait: bool,
+
+    /// Suppress status messages
+    #[arg(short, long, global = true)]
+    quiet: bool,
+}
+
+#[derive(clap::Args, Debug)]
+struct InputPath {
+    /// Positional alternative to `--input`
+    #[arg(value_name = "INPUT", conflicts_with = "input")]
+    input_path: Option<String>,
 }
 
-impl From<char> for Answer {
-    fn from(value: char) -> Answer {
-        match value {
-            'A' => Answer::A,
-            'B' => Answer::B,
-            'C' => Answer::C,
-            'D' => Answer::D,
-            'X' => Answer::X,
-            _ => panic!("Invalid letter: {}", value),
+#[derive(clap::Args, Debug)]
+struct SearchArgs {
+    #[command(flatten)]
+    input: InputPath,
+
+    /// File to write the possible answer keys to
+    #[arg(short, long, default_value = "possible_answers.txt")]
+    output: String,
+
+    /// Also write which questions are resolved and which answers remain for
+    /// the others to this JSON file
+    #[arg(long, value_name = "FILE", conflicts_with = "stream")]
+    report: Option<String>,
+
+    /// Also write the attempts, choices, reduction steps and candidates of
+    /// the run to this JSON file
+    #[arg(long, value_name = "FILE", conflicts_with = "stream")]
+    session_report: Option<String>,
+
+    /// Directory to write the output file in, created if missing; `--output`
+    /// is then relative to it
+    #[arg(long, value_name = "DIR")]
+    output_dir: Option<String>,
+
+    /// Format of the output file
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Text)]
+    format: OutputFormat,
+
+    /// Order of the answers when sorting the output keys, such as `XABCD`;
+    /// unlisted answers come last
+    #[arg(long, value_name = "LETTERS", value_parser = parse_sort_order)]
+    sort_order: Option<SortOrder>,
+
+    /// Refuse to search if the top attempt allows more candidate keys than this
+    #[arg(long, value_name = "N", default_value_t = 100_000_000)]
+    max_candidates: u128,
+
+    /// Before searching, fix each question to the answer that more than this
+    /// fraction of the better half of the attempts gave, e.g. 0.9. This is a
+    /// guess that shrinks the search: a wrong lock rules out the true key, so
+    /// it is off by default
+    #[arg(long, value_name = "FRACTION", value_parser = parse_threshold, conflicts_with = "resume")]
+    lock_threshold: Option<f64>,
+
+    /// Turn off guesses such as `--lock-threshold`, leaving only the exact search
+    #[arg(long)]
+    no_heuristics: bool,
+
+    /// Only write the N keys that best match the most common answer to each
+    /// question, ties broken lexicographically
+    #[arg(long, value_name = "N")]
+    top_n: Option<usize>,
+
+    /// Reduce the candidate keys saved in this file by the attempts, instead
+    /// of generating them afresh
+    #[arg(long, value_name = "CANDIDATES", conflicts_with = "known")]
+    resume: Option<String>,
+
+    /// Write keys to the output as they are found instead of collecting them
+    /// first, for sets too large to hold in memory. Keys are not sorted and
+    /// no summary is printed
+    #[arg(
+        long,
+        conflicts_with_all = ["resume", "top_n", "multi_answer", "format", "sort_order", "verbose"]
+    )]
+    stream: bool,
+
+    /// Warn that the attempts leave the key wide open when more candidate
+    /// keys than this are left
+    #[arg(long, value_name = "N", default_value_t = 100)]
+    warn_above: usize,
+
+    /// Report how many keys each attempt eliminated
+    #[arg(short, long)]
+    verbose: bool,
+}
+
+#[derive(clap::Args, Debug)]
+struct GenerateArgs {
+    /// Number of attempts
+    #[arg(long, value_name = "N", default_value_t = 20)]
+    attempts: usize,
+
+    /// Average number of wrong answers in an attempt
+    #[arg(long, value_name = "M", default_value_t = 3.0)]
+    mistakes_mean: f64,
+
+    /// Seed for the random numbers, the same one gives the same quiz
+    #[arg(long, value_name = "S", default_value_t = 0)]
+    seed: u64,
+
+    /// File to write the attempts to
+    #[arg(short, long, value_name = "FILE", default_value = "attempts.txt")]
+    output: String,
+
+    /// File to write the hidden key to
+    #[arg(long, value_name = "FILE", default_value = "key.txt")]
+    key_output: String,
+}
+
+fn parse_delimiter(s: &str) -> Result<char, String> {
+    match s {
+        "\\t" | "tab" => Ok('\t'),
+        _ => {
+            let mut chars = s.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Ok(c),
+                _ => Err(format!("Delimiter must be a single character: {}", s)),
+            }
         }
     }
 }
 
-#[derive(PartialEq, Eq, Debug, Clone)]
-struct QuizAttempt {
-    answers: Vec<Answer>,
-    score: i32,
+fn parse_blank_char(s: &str) -> Result<char, String> {
+    let mut chars = s.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if c == 'X' || Answer::try_from(c).is_err() => Ok(c),
+        (Some(c), None) => Err(format!("{} is an answer, not a blank", c)),
+        _ => Err(format!("Blank marker must be a single character: {}", s)),
+    }
 }
 
-impl PartialOrd for QuizAttempt {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.score.cmp(&other.score))
+/// 0-based question indices.
+#[derive(Clone, Debug, Default)]
+struct Questions(Vec<usize>);
+
+fn parse_questions(s: &str) -> Result<Questions, String> {
+    let number = |n: &str| match n.trim().parse::<usize>() {
+        Ok(n) if n > 0 => Ok(n - 1),
+        _ => Err(format!("Not a question number: {}", n)),
+    };
+
+    let mut questions = vec![];
+    for part in s.split(',') {
+        match part.split_once('-') {
+            Some((from, to)) => questions.extend(number(from)?..=number(to)?),
+            None => questions.push(number(part)?),
+        }
     }
+    Ok(Questions(questions))
 }
 
-impl Ord for QuizAttempt {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.score.cmp(&other.score)
+#[derive(Clone, Debug, Default)]
+struct SortOrder(Vec<Answer>);
+
+fn parse_sort_order(s: &str) -> Result<SortOrder, String> {
+    let mut order = vec![];
+    for c in s.chars() {
+        let ans = Answer::try_from(c).map_err(|e| e.to_string())?;
+        if order.contains(&ans) {
+            return Err(format!("{} appears twice", ans));
+        }
+        order.push(ans);
     }
+    Ok(SortOrder(order))
 }
 
-#[derive(PartialEq, PartialOrd, Ord, Eq, Debug, Clone)]
-struct AnswerKey {
-    answers: Vec<Answer>,
+fn parse_threshold(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(t) if (0.5..1.0).contains(&t) => Ok(t),
+        _ => Err(format!("Expected a fraction from 0.5 up to 1: {}", s)),
+    }
 }
 
-impl AnswerKey {
-    fn as_string(&self) -> String {
-        self.answers.iter().map(|ans| ans.to_string()).collect()
+/// Known answers by 0-based question.
+#[derive(Clone, Debug, Default)]
+struct Known(Vec<(usize, Answer)>);
+
+fn parse_known(s: &str) -> Result<Known, String> {
+    let mut known = vec![];
+    for part in s.split(',') {
+        let (q, ans) = part
+            .split_once('=')
+            .ok_or_else(|| format!("Expected QUESTION=ANSWER: {}", part))?;
+        let q = match q.trim().parse::<usize>() {
+            Ok(q) if q > 0 => q - 1,
+            _ => return Err(format!("Not a question number: {}", q)),
+        };
+        let mut letters = ans.trim().chars();
+        let ans = match (letters.next().map(Answer::try_from), letters.next()) {
+            (Some(Ok(ans)), None) if ans != Answer::X => ans,
+            _ => return Err(format!("Not an answer: {}", ans)),
+        };
+        known.push((q, ans));
     }
+    Ok(Known(known))
 }
 
-struct AnswerKeySet {
-    keys: Vec<AnswerKey>,
+/// `1240000` as `1,240,000`.
+// `is_multiple_of` would need Rust 1.87
+#[allow(clippy::manual_is_multiple_of)]
+fn thousands(n: usize) -> String {
+    let digits = n.to_string();
+    let mut out = String::new();
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % 3 == 0 {
+            out.push(',');
+        }
+        out.push(c);
+    }
+    out
 }
 
-impl From<Vec<Answer>> for AnswerKey {
-    fn from(value: Vec<Answer>) -> AnswerKey {
-        AnswerKey { answers: value }
+fn warn_if_underdetermined(found: usize, limit: usize) {
+    if found > limit {
+        warn!(
+            "{} candidate keys are left, so the attempts are far from settling the key. \
+             Consider adding more attempts or a --known constraint.",
+            thousands(found)
+        );
     }
 }
 
-impl From<Vec<AnswerKey>> for AnswerKeySet {
-    fn from(value: Vec<AnswerKey>) -> AnswerKeySet {
-        AnswerKeySet { keys: value }
+fn report_contradiction(attempt: &QuizAttempt) -> ! {
+    error!(
+        "No answer key is consistent with the given attempts — your score data may be wrong.\n\
+         No candidates were left after the attempt {}{} with score {}.",
+        attempt.as_string(),
+        attempt
+            .id()
+            .map_or(String::new(), |id| format!(" by {}", id)),
+        attempt.score()
+    );
+    process::exit(CONTRADICTION);
+}
+
+fn exit_on_write_error(path: &str, written: io::Result<()>) {
+    if let Err(e) = written {
+        error!("Could not write to {}: {}", path, e);
+        process::exit(1);
     }
 }
 
-impl QuizAttempt {
-    fn from_string(string: &str, score: i32) -> QuizAttempt {
-        if string.len() < score as usize {
-            panic!(
-                "Impossible score: {} with test length {}",
-                score,
-                string.len()
-            )
+fn verify(key: &str, attempts: &[QuizAttempt]) -> bool {
+    let key: AnswerKey = match key.parse() {
+        Ok(key) => key,
+        Err(e) => {
+            error!("Could not parse the key to verify: {}", e);
+            process::exit(1);
         }
-        QuizAttempt {
-            answers: string
-                .to_uppercase()
-                .chars()
-                .map(|c| Answer::from(c))
-                .collect(),
-            score: score,
+    };
+    if key.answers().len() != attempts[0].answers().len() {
+        error!(
+            "The key has {} answers but the attempts have {}.",
+            key.answers().len(),
+            attempts[0].answers().len()
+        );
+        process::exit(1);
+    }
+
+    for (i, att) in attempts.iter().enumerate() {
+        if att.check(&key) {
+            println!(
+                "Attempt #{} {}: ok (score {})",
+                i + 1,
+                att.as_string(),
+                att.score()
+            );
+        } else {
+            println!(
+                "Attempt #{} {}: VIOLATED (recorded {}, key implies {} with {} correct)",
+                i + 1,
+                att.as_string(),
+                att.score(),
+                att.implied_score(&key),
+                att.score_against(&key)
+            );
         }
     }
 
-    fn from_list(list: &[&str]) -> QuizAttempt {
-        QuizAttempt::from_string(
-            list[0],
-            list[1]
-                .parse::<i32>()
-                .expect(&format!("Score is not a number! {}", list[1])),
-        )
+    let violated = attempts.iter().filter(|att| !att.check(&key)).count();
+    if violated == 0 {
+        println!(
+            "{} is consistent with all {} attempts.",
+            key.as_string(),
+            attempts.len()
+        );
+    } else {
+        println!(
+            "{} violates {} of {} attempts.",
+            key.as_string(),
+            violated,
+            attempts.len()
+        );
     }
 
-    fn check(&self, key: &AnswerKey) -> bool {
-        if self.answers.len() != key.answers.len() {
-            panic!("Unmatched lengths!");
+    key.consistent_with(attempts)
+}
+
+fn print_histogram(attempts: &[QuizAttempt]) {
+    let histogram = match QuizAttempt::answer_histogram(attempts) {
+        Ok(histogram) => histogram,
+        Err(e) => {
+            error!("{}", e);
+            process::exit(1);
         }
-        self.answers
-            .iter()
-            .zip(&key.answers)
-            .map(|(&x, &y)| if x == y { 1 } else { 0 })
-            .sum::<i32>()
-            == self.score
-    }
+    };
+    let letters: Vec<Answer> = histogram
+        .iter()
+        .flat_map(|counts| counts.keys().copied())
+        .sorted()
+        .dedup()
+        .collect();
 
-    fn generate_valid_set(&self) -> AnswerKeySet {
-        let num_mistakes = self.answers.len() - self.score as usize;
+    let plurality = QuizAttempt::plurality_answers(attempts).expect("Checked by the histogram");
 
-        let mut answers_to_try: Vec<Vec<&Answer>> = [Answer::A, Answer::B, Answer::C, Answer::D]
-            .iter()
-            .combinations_with_replacement(num_mistakes)
-            .flat_map(|comb| comb.into_iter().permutations(num_mistakes))
-            .collect();
-        answers_to_try.sort();
-        answers_to_try.dedup();
-
-        AnswerKeySet::from({
-            let mut small_set = (0..self.answers.len())
-                .combinations(num_mistakes)
-                .flat_map(|possible_mistakes| {
-                    let mut small_key = vec![];
-                    // generate 2^n solutions
-                    for ans in &answers_to_try {
-                        let mut this_key = self.answers.clone();
-
-                        for (&add, &a) in possible_mistakes.clone().iter().zip(ans) {
-                            this_key[add] = a.clone();
-                        }
-
-                        small_key.push(AnswerKey::from(this_key));
-                    }
-
-                    small_key
-                })
-                .filter(|key| !key.answers.contains(&Answer::X))
-                .collect::<Vec<AnswerKey>>();
-            small_set.sort();
-            small_set.dedup();
-            small_set
-        })
+    print!("{:>4}", "Q");
+    for ans in &letters {
+        print!("{:>5}", ans.to_string());
+    }
+    println!("  most common");
+    for (q, counts) in histogram.iter().enumerate() {
+        print!("{:>4}", q + 1);
+        for ans in &letters {
+            print!("{:>5}", counts.get(ans).unwrap_or(&0));
+        }
+        println!(
+            "  {}",
+            plurality[q].map_or("-".to_string(), |ans| ans.to_string())
+        );
     }
 }
 
-impl AnswerKeySet {
-    fn reduce(mut self, attempt: &QuizAttempt) -> AnswerKeySet {
-        self.keys = self
-            .keys
-            .iter()
-            .filter(|k| attempt.check(k))
-            .cloned()
-            .collect();
+fn print_discrimination(attempts: &[QuizAttempt]) {
+    let (plurality, discrimination) = match QuizAttempt::plurality_answers(attempts)
+        .and_then(|p| Ok((p, QuizAttempt::question_discrimination(attempts)?)))
+    {
+        Ok(analysis) => analysis,
+        Err(e) => {
+            error!("{}", e);
+            process::exit(1);
+        }
+    };
 
-        self
+    println!("{:>4}  {:>11}  {:>6}", "Q", "most common", "r");
+    let by_discrimination = (0..discrimination.len())
+        .sorted_by(|&a, &b| discrimination[b].total_cmp(&discrimination[a]));
+    for q in by_discrimination {
+        println!(
+            "{:>4}  {:>11}  {:>6.3}",
+            q + 1,
+            plurality[q].map_or("-".to_string(), |ans| ans.to_string()),
+            discrimination[q]
+        );
     }
+}
 
-    fn save_to_file(&self, filename: &str) {
-        let mut current_path = env::current_exe().expect("Could not get current path");
-        current_path.pop();
-        current_path.push(filename);
+fn read_interactively(options: &LoadOptions) -> Vec<QuizAttempt> {
+    let mut attempts: Vec<QuizAttempt> = vec![];
+    let mut lines = stdin().lock().lines();
 
-        let f = File::create(current_path).expect("Could not create output file!");
-        let mut f = BufWriter::new(f);
+    loop {
+        print!(
+            "Enter answers{}score (blank to finish): ",
+            options.delimiter
+        );
+        stdout().flush().expect("Could not write to stdout!");
+        let line = match lines.next() {
+            Some(Ok(line)) if !line.trim().is_empty() => line,
+            _ => break,
+        };
 
-        self.keys.iter().for_each(|key| {
-            writeln!(f, "{}", key.as_string()).expect("Could not write to file!");
-        });
+        match options.parse_line(&line) {
+            Ok(att) => match attempts.first() {
+                Some(first) if first.answers().len() != att.answers().len() => println!(
+                    "Skipped: this attempt has {} answers but the first had {}.",
+                    att.answers().len(),
+                    first.answers().len()
+                ),
+                _ => attempts.push(att),
+            },
+            Err(e) => println!("Skipped: {}", e),
+        }
+    }
+
+    attempts.sort();
+    attempts.reverse();
+    attempts
+}
+
+#[cfg(feature = "progress")]
+fn progress_bar(len: u64, quiet: bool) -> indicatif::ProgressBar {
+    if quiet {
+        return indicatif::ProgressBar::hidden();
     }
+    indicatif::ProgressBar::new(len).with_style(
+        indicatif::ProgressStyle::with_template("{msg:>12} [{bar:40}] {pos}/{len} ({eta})")
+            .expect("Invalid progress template")
+            .progress_chars("=> "),
+    )
 }
 
-fn extract_attempts_from_file(filename: &str) -> Vec<QuizAttempt> {
-    let mut current_path = env::current_exe().expect("Could not get current path");
-    current_path.pop();
-    current_path.push(filename);
+// only when someone is there to press a key
+fn wait_for_keypress(args: &Common) {
+    if args.no_wait || !stdin().is_terminal() || !stdout().is_terminal() {
+        return;
+    }
+    if !args.quiet {
+        println!("Press any key to end...");
+    }
+    let _ = stdin().read(&mut [0]);
+}
 
-    let mut loaded_attempts: Vec<QuizAttempt> = fs::read_to_string(current_path)
-        .expect(&format!("Could not read file {}", filename))
-        .lines()
-        .map(|str| QuizAttempt::from_list(&str.split(",").collect::<Vec<&str>>()))
-        .collect();
+#[cfg(feature = "rand")]
+fn generate(args: &Common, gen: &GenerateArgs, length: usize) -> ! {
+    let true_false = args.true_false.clone().unwrap_or_default().0;
+    let letters = &Answer::choices()[..args.choices as usize];
+    let choices = Choices::per_question(
+        (0..length)
+            .map(|q| {
+                if true_false.contains(&q) {
+                    TRUE_FALSE.to_vec()
+                } else {
+                    letters.to_vec()
+                }
+            })
+            .collect(),
+    );
+    let (key, attempts) =
+        answerkey_search::synthetic_quiz(&choices, gen.attempts, gen.mistakes_mean, gen.seed);
 
-    // sanity checks
-    // quiz attempts must all have the same length
-    let lens: Vec<usize> = loaded_attempts
+    let lines: String = attempts
         .iter()
-        .map(|att| att.answers.len())
+        .map(|att| format!("{}{}{}\n", att.as_string(), args.delimiter, att.score()))
         .collect();
+    for (path, contents) in [
+        (&gen.output, lines),
+        (&gen.key_output, format!("{}\n", key.as_string())),
+    ] {
+        if let Err(e) = fs::write(path, contents) {
+            error!("Could not write {}: {}", path, e);
+            process::exit(1);
+        }
+    }
+    info!(
+        "Wrote {} attempts to {} and their key to {}.",
+        attempts.len(),
+        gen.output,
+        gen.key_output
+    );
+    process::exit(0);
+}
 
-    if &lens.iter().min() != &lens.iter().max() {
-        panic!("The lengths of the answers are not all the same!")
+/// Exit like `clap::Error::exit`, but with status 1 for usage errors as for
+/// any other bad input, since clap's 2 means several keys are possible.
+fn exit_with(e: clap::Error) -> ! {
+    match e.kind() {
+        ErrorKind::DisplayHelp | ErrorKind::DisplayVersion => e.exit(),
+        _ => {
+            let _ = e.print();
+            process::exit(1);
+        }
     }
+}
 
-    // sort by score
-    loaded_attempts.sort();
-    loaded_attempts.reverse();
+/// Like `Args::parse`, also rejecting the top-level search options along with
+/// a subcommand, where they would be ignored.
+fn parse_args() -> Args {
+    let matches = Args::command()
+        .try_get_matches()
+        .unwrap_or_else(|e| exit_with(e));
+    let args = Args::from_arg_matches(&matches).unwrap_or_else(|e| exit_with(e));
+    if let Some(name) = matches.subcommand_name() {
+        let search = SearchArgs::augment_args(clap::Command::new("search"));
+        for arg in search.get_arguments() {
+            if matches.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine) {
+                exit_with(Args::command().error(
+                    ErrorKind::ArgumentConflict,
+                    format!(
+                            "{} cannot come before `{}`",
+                            match arg.get_long() {
+                                Some(long) => format!("--{}", long),
+                                None => arg
+                                    .get_value_names()
+                                    .and_then(|names| names.first())
+                                    .map_or(arg.get_id().to_string(), |name| name.to_string()),
+                            },
+                            name
+                    ),
+                ));
+            }
+        }
+    }
+    args
+}
 
-    loaded_attempts
+/// Progress at `info`, only warnings and errors with `--quiet`. `RUST_LOG`
+/// takes precedence.
+fn init_logging(quiet: bool) {
+    env_logger::Builder::new()
+        .filter_level(if quiet {
+            LevelFilter::Warn
+        } else {
+            LevelFilter::Info
+        })
+        .parse_default_env()
+        .format(|buf, record| match record.level() {
+            Level::Info => writeln!(buf, "{}", record.args()),
+            Level::Warn => writeln!(buf, "warning: {}", record.args()),
+            level => writeln!(buf, "{}: {}", level.as_str().to_lowercase(), record.args()),
+        })
+        .init();
 }
 
 fn main() {
-    println!("Reading attempts from file: attempts.txt...");
-    let base = extract_attempts_from_file("attempts.txt");
+    let Args {
+        command,
+        common: args,
+        search,
+    } = parse_args();
+    // with a subcommand the top-level search options are only the defaults
+    let (mode, input_path, mut search) = match command {
+        None => (Mode::Search, search.input.input_path.clone(), search),
+        Some(Command::Search(search)) => (Mode::Search, search.input.input_path.clone(), search),
+        Some(Command::Verify { key, input }) => (Mode::Verify(key), input.input_path, search),
+        Some(Command::Stats { input }) => (Mode::Stats, input.input_path, search),
+        Some(Command::Estimate { input }) => (Mode::Estimate, input.input_path, search),
+        Some(Command::Generate(gen)) => {
+            init_logging(args.quiet);
+            let length = match args.length {
+                Some(0) => {
+                    error!("A quiz needs at least one question.");
+                    process::exit(1);
+                }
+                Some(length) => length,
+                None => {
+                    error!("generate needs the number of questions, given with --length.");
+                    process::exit(1);
+                }
+            };
+            #[cfg(feature = "rand")]
+            generate(&args, &gen, length);
+            #[cfg(not(feature = "rand"))]
+            {
+                let _ = (gen, length);
+                error!("generate needs answerkey-search to be built with the `rand` feature.");
+                process::exit(1);
+            }
+        }
+    };
+    // not a clap conflict, as `generate` has no INPUT
+    if input_path.is_some() && (args.interactive || args.answers.is_some()) {
+        exit_with(Args::command().error(
+            ErrorKind::ArgumentConflict,
+            "INPUT cannot be used with --interactive or --answers",
+        ));
+    }
+    init_logging(args.quiet);
 
-    println!(
-        "Loaded {} answers of length {}",
-        base.len(),
-        base[0].answers.len()
-    );
+    #[cfg(not(feature = "serde"))]
+    for (given, flag) in [
+        (&search.report, "--report"),
+        (&search.session_report, "--session-report"),
+    ] {
+        if given.is_some() {
+            error!(
+                "{} needs answerkey-search to be built with the `serde` feature.",
+                flag
+            );
+            process::exit(1);
+        }
+    }
 
-    println!("Searching for possible answers (This could take a while)...");
+    if let Some(dir) = &search.output_dir {
+        search.output = Path::new(dir).join(&search.output).display().to_string();
+    }
+    if let (Mode::Search, Some(parent)) = (&mode, Path::new(&search.output).parent()) {
+        if let Err(e) = fs::create_dir_all(parent) {
+            error!("Could not create {}: {}", parent.display(), e);
+            process::exit(1);
+        }
+    }
 
-    // TODO: This should probably be implemented in AnswerKeySet
-    let highest = base[0].generate_valid_set();
+    // results go to stdout, progress and diagnostics are logged to stderr
+    macro_rules! status {
+        ($($arg:tt)*) => {
+            if !args.quiet {
+                println!($($arg)*);
+            }
+        };
+    }
 
-    let answerset = &base[1..base.len()]
-        .iter()
-        .fold(highest, |ans_set, att| ans_set.reduce(att));
+    let input = input_path.as_ref().unwrap_or(&args.input);
+    let source = if args.interactive {
+        "interactive input".to_string()
+    } else if let (Some(answers), Some(scores)) = (&args.answers, &args.scores) {
+        format!("{} and {}", answers, scores)
+    } else if input == "-" {
+        "stdin".to_string()
+    } else {
+        input.to_string()
+    };
 
-    println!(
-        "Found {} possible solutions! Writing to possible_answers.txt...",
-        answerset.keys.len()
-    );
+    if args.blank_char == args.delimiter {
+        error!(
+            "{:?} cannot be both the delimiter and the blank marker.",
+            args.delimiter
+        );
+        process::exit(1);
+    }
+    let options = LoadOptions {
+        delimiter: args.delimiter,
+        scoring: ScoringScheme {
+            correct: args.correct_points,
+            wrong: args.wrong_points,
+            blank: args.blank_points,
+            weights: args.weights.clone(),
+        },
+        dedup_attempts: args.dedup_attempts,
+        pad_short: args.pad_short,
+        blank: args.blank_char,
+        comment: Some(args.comment_char),
+        length: args.length,
+    };
 
-    answerset.save_to_file("possible_answers.txt");
+    let loading = Instant::now();
+    let loaded = if args.interactive {
+        let attempts = read_interactively(&options);
+        if attempts.is_empty() {
+            error!("No attempts entered.");
+            process::exit(1);
+        }
+        Ok(LoadedAttempts {
+            attempts,
+            duplicates: vec![],
+            padded: vec![],
+        })
+    } else if let (Some(answers), Some(scores)) = (&args.answers, &args.scores) {
+        info!(
+            "Reading answers from {} and scores from {}...",
+            answers, scores
+        );
+        LoadedAttempts::from_parallel_files(answers, scores, &options)
+    } else {
+        let format = args.input_format.unwrap_or(if input.ends_with(".json") {
+            InputFormat::Json
+        } else {
+            InputFormat::Text
+        });
+        if input == "-" {
+            info!("Reading attempts from stdin...");
+        } else {
+            info!("Reading attempts from file: {}...", input);
+        }
+        match format {
+            InputFormat::Text if input == "-" => {
+                LoadedAttempts::from_reader(stdin().lock(), &options)
+            }
+            InputFormat::Text => LoadedAttempts::from_file(input, &options),
+            #[cfg(feature = "serde")]
+            InputFormat::Json if input == "-" => {
+                LoadedAttempts::from_json_reader(stdin().lock(), &options)
+            }
+            #[cfg(feature = "serde")]
+            InputFormat::Json => LoadedAttempts::from_json_file(input, &options),
+            #[cfg(not(feature = "serde"))]
+            InputFormat::Json => {
+                error!("JSON input needs answerkey-search to be built with the `serde` feature.");
+                process::exit(1);
+            }
+        }
+    };
+    let loaded = match loaded {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            error!("{}", e);
+            process::exit(1);
+        }
+    };
 
-    println!("Press any key to end...");
-    stdin().read(&mut [0]).unwrap();
-}
+    for (line, earlier) in &loaded.duplicates {
+        warn!(
+            "line {} repeats the attempt on line {}{}.",
+            line,
+            earlier,
+            if args.dedup_attempts {
+                ", dropping it"
+            } else {
+                ""
+            }
+        );
+    }
+    for (line, given) in &loaded.padded {
+        warn!(
+            "line {} has only {} answers, the rest are treated as blanks.",
+            line, given
+        );
+    }
+    let mut base: Vec<QuizAttempt> = loaded
+        .attempts
+        .into_iter()
+        .map(|att| att.with_tolerance(args.score_tolerance))
+        .collect();
 
-#[cfg(test)]
-mod tests {
-    use crate::{Answer, AnswerKey, QuizAttempt};
-    use itertools::Itertools;
+    info!(
+        "Loaded {} answers of length {} in {:.1}s",
+        base.len(),
+        base[0].answers().len(),
+        loading.elapsed().as_secs_f64()
+    );
 
-    #[test]
-    fn answer_from_str() {
-        assert_eq!(Answer::from('A'), Answer::A);
-        assert_eq!(Answer::from('B'), Answer::B);
-        assert_eq!(Answer::from('C'), Answer::C);
-        assert_eq!(Answer::from('D'), Answer::D);
-        assert_eq!(Answer::from('X'), Answer::X);
+    match &mode {
+        Mode::Stats => {
+            print_histogram(&base);
+            println!();
+            print_discrimination(&base);
+            wait_for_keypress(&args);
+            process::exit(0);
+        }
+        Mode::Verify(key) => {
+            let consistent = verify(key, &base);
+            wait_for_keypress(&args);
+            process::exit(if consistent { 0 } else { CONTRADICTION });
+        }
+        Mode::Search | Mode::Estimate => {}
     }
 
-    #[test]
-    #[should_panic]
-    fn invalid_answer() {
-        Answer::from('E');
+    let len = match QuizAttempt::validate_uniform_length(&base) {
+        Ok(len) => len,
+        Err(e) => {
+            error!("{}", e);
+            process::exit(1);
+        }
+    };
+    let true_false = args.true_false.clone().unwrap_or_default().0;
+    if let Some(&q) = true_false.iter().find(|&&q| q >= len) {
+        error!(
+            "Question {} is marked true/false but the quiz only has {} questions.",
+            q + 1,
+            len
+        );
+        process::exit(1);
+    }
+    let letters = &Answer::choices()[..args.choices as usize];
+    for (i, att) in base.iter().enumerate() {
+        let outside = att.answers().iter().enumerate().find(|&(q, ans)| {
+            *ans != Answer::X && !true_false.contains(&q) && !letters.contains(ans)
+        });
+        if let Some((q, ans)) = outside {
+            error!(
+                "{} answers {} to question {}, but there are only {} choices ({}-{}). Try --choices.",
+                att.id().map_or(format!("Attempt #{}", i + 1), |id| format!("Student {}'s attempt", id)),
+                ans,
+                q + 1,
+                letters.len(),
+                letters[0],
+                letters[letters.len() - 1]
+            );
+            process::exit(1);
+        }
     }
+    // F is also false on true/false questions, which would hide unused letters
+    if true_false.is_empty() {
+        let observed = QuizAttempt::observed_alphabet(&base);
+        let used = letters.iter().rposition(|ans| observed.contains(ans));
+        if let Some(last) = used.filter(|&last| last + 1 < letters.len() && last > 0) {
+            warn!(
+                "The attempts only use {}-{}; if the quiz has {} choices, pass --choices {}.",
+                letters[0],
+                letters[last],
+                last + 1,
+                last + 1
+            );
+        }
+    }
+    let choices = Choices::per_question(
+        (0..len)
+            .map(|q| {
+                if true_false.contains(&q) {
+                    TRUE_FALSE.to_vec()
+                } else {
+                    letters.to_vec()
+                }
+            })
+            .collect(),
+    );
+    let known = args.known.clone().unwrap_or_default().0;
+    for &(q, ans) in &known {
+        if q >= len {
+            error!(
+                "Question {} has a known answer but the quiz only has {} questions.",
+                q + 1,
+                len
+            );
+            process::exit(1);
+        }
+        if !choices.at(q).contains(&ans) {
+            error!("{} is not one of the choices for question {}.", ans, q + 1);
+            process::exit(1);
+        }
+    }
+    let choices = choices.with_known(&known);
 
-    #[test]
-    fn crosscheck_works_for_valid() {
-        let key = AnswerKey {
-            answers: vec![Answer::A, Answer::B],
-        };
-        let att = QuizAttempt {
-            score: 1,
-            answers: vec![Answer::A, Answer::A],
-        };
-        assert_eq!(att.check(&key), true)
+    let locked: Vec<(usize, Answer)> = match search.lock_threshold {
+        Some(threshold) if !search.no_heuristics => QuizAttempt::agreed_answers(&base, threshold)
+            .expect("lengths were checked above")
+            .into_iter()
+            .enumerate()
+            .filter_map(|(q, ans)| Some((q, ans?)))
+            .filter(|&(q, ans)| choices.at(q).len() > 1 && choices.at(q).contains(&ans))
+            .collect(),
+        _ => vec![],
+    };
+    if !locked.is_empty() {
+        warn!(
+            "Locked {} of {} questions to the answer most top attempts agree on; if any lock is wrong, the true key is ruled out.",
+            locked.len(),
+            len
+        );
     }
+    let choices = choices.with_known(&locked);
 
-    #[test]
-    fn crosscheck_works_for_invalid() {
-        let key = AnswerKey {
-            answers: vec![Answer::A, Answer::B],
+    let generating = Instant::now();
+    let (highest, first) = if let Some(path) = &search.resume {
+        let resumed = match AnswerKeySet::load_from_file(path) {
+            Ok(resumed) => resumed,
+            Err(e) => {
+                error!("{}", e);
+                process::exit(1);
+            }
         };
-        let att = QuizAttempt {
-            score: 2,
-            answers: vec![Answer::A, Answer::A],
+        if let Some(key) = resumed.iter().find(|key| key.answers().len() != len) {
+            error!(
+                "The candidate key {} in {} does not match the quiz length of {}.",
+                key.as_string(),
+                path,
+                len
+            );
+            process::exit(1);
+        }
+        if resumed.is_empty() {
+            error!("There are no candidate keys in {}.", path);
+            process::exit(CONTRADICTION);
+        }
+        info!(
+            "Resuming from {} candidate keys in {}.",
+            resumed.len(),
+            path
+        );
+        (resumed, 0)
+    } else {
+        // rule out what the scores alone rule out, which assumes one answer per question
+        let choices = if args.multi_answer > 0 {
+            choices.clone()
+        } else {
+            match narrow_choices(&base, &choices) {
+                Some(narrowed) => narrowed,
+                None if !locked.is_empty() => {
+                    error!(
+                        "No answer key is consistent with the attempts and the locked answers; try a higher --lock-threshold or --no-heuristics."
+                    );
+                    process::exit(CONTRADICTION);
+                }
+                None if !known.is_empty() => {
+                    error!(
+                        "No answer key is consistent with both the attempts and the known answers."
+                    );
+                    process::exit(CONTRADICTION);
+                }
+                None => {
+                    error!(
+                        "No answer key is consistent with the given attempts — your score data may be wrong."
+                    );
+                    process::exit(CONTRADICTION);
+                }
+            }
         };
-        att.check(&key);
-    }
+        let fixed = (0..len).filter(|&q| choices.at(q).len() == 1).count();
+        if fixed > 0 {
+            info!("The scores alone fix {} of {} questions.", fixed, len);
+        }
 
-    #[test]
-    #[should_panic]
-    fn crosscheck_works_for_invalid_length() {
-        let key = AnswerKey {
-            answers: vec![Answer::A, Answer::B],
+        // generate from the attempt allowing the fewest keys, reduce by the rest in order
+        let perfect = base
+            .iter()
+            .position(|att| att.num_mistakes() == 0 && att.tolerance() == 0);
+        let seed_index = perfect.unwrap_or_else(|| {
+            let seed = QuizAttempt::best_seed_with(&base, &choices);
+            base.iter().position(|att| std::ptr::eq(att, seed)).unwrap()
+        });
+        base[..=seed_index].rotate_right(1);
+        if perfect.is_some() && args.multi_answer == 0 {
+            info!("Top attempt scored full marks — answer key determined directly.");
+        }
+
+        let estimate = base[0].estimate_candidates_with(&choices);
+        if mode == Mode::Estimate {
+            println!("attempts: {}", base.len());
+            println!("quiz length: {}", len);
+            println!(
+                "seed attempt: {} (score {})",
+                base[0].as_string(),
+                base[0].score()
+            );
+            println!(
+                "candidate keys: {}{}",
+                if args.multi_answer > 0 {
+                    "at least "
+                } else {
+                    ""
+                },
+                estimate
+            );
+            process::exit(0);
+        }
+        if estimate > search.max_candidates {
+            error!(
+                "The seed attempt {} (score {}) allows about {} candidate keys, more than the limit of {}.\n\
+                 Raise --max-candidates to search anyway.",
+                base[0].as_string(),
+                base[0].score(),
+                estimate,
+                search.max_candidates
+            );
+            process::exit(1);
+        }
+
+        info!(
+            "The seed attempt {} allows {} candidate keys.",
+            base[0].as_string(),
+            if args.multi_answer > 0 {
+                format!("at least {}", estimate)
+            } else {
+                estimate.to_string()
+            }
+        );
+        info!("Searching for possible answers (This could take a while)...");
+
+        if search.stream {
+            let written = fs::File::create(&search.output).and_then(|f| {
+                let mut w = BufWriter::new(f);
+                writeln!(w, "# input: {}", source)?;
+                writeln!(w, "# quiz length: {}", len)?;
+                base[0].write_valid_keys(&choices, &base[1..], w)
+            });
+            let written = match written {
+                Ok(written) => written,
+                Err(e) => {
+                    error!("Could not write to {}: {}", search.output, e);
+                    process::exit(1);
+                }
+            };
+            status!(
+                "Found {} possible solutions, written to {}.",
+                written,
+                search.output
+            );
+            warn_if_underdetermined(written as usize, search.warn_above);
+            wait_for_keypress(&args);
+            process::exit(match written {
+                0 => CONTRADICTION,
+                1 => 0,
+                _ => AMBIGUOUS,
+            });
+        }
+
+        #[cfg(feature = "progress")]
+        let bar =
+            progress_bar(base[0].mistake_position_sets(), args.quiet).with_message("Generating");
+        let generated = if args.multi_answer > 0 {
+            base[0].generate_multi_answer_set(&choices, args.multi_answer)
+        } else {
+            base[0].generate_valid_set_with_progress(&choices, || {
+                #[cfg(feature = "progress")]
+                bar.inc(1);
+            })
         };
-        let att = QuizAttempt {
-            score: 2,
-            answers: vec![Answer::A],
+        let highest = match generated {
+            Ok(set) => set,
+            Err(e) => {
+                error!("{}", e);
+                process::exit(1);
+            }
         };
-        assert_eq!(att.check(&key), false)
+        #[cfg(feature = "progress")]
+        bar.finish_and_clear();
+
+        if highest.is_empty() {
+            report_contradiction(&base[0]);
+        }
+        (highest, 1)
+    };
+    let generated = highest.len();
+    let generating = generating.elapsed();
+
+    let reducing = Instant::now();
+
+    #[cfg(feature = "progress")]
+    let bar = progress_bar((base.len() - first) as u64, args.quiet).with_message("Reducing");
+    let reduced = highest.reduce_until_unique_with_progress(&base[first..], || {
+        #[cfg(feature = "progress")]
+        bar.inc(1);
+    });
+    #[cfg(feature = "progress")]
+    bar.finish_and_clear();
+    let (answerset, stats) = match reduced {
+        Ok(reduced) => reduced,
+        Err(SolveError::NoConsistentKey { attempt }) => {
+            report_contradiction(&base[first + attempt])
+        }
+        Err(e) => {
+            error!("{}", e);
+            process::exit(1);
+        }
+    };
+    // attempts applied, counting the seed
+    let used = first + stats.len();
+    info!(
+        "{} {} candidates in {:.1}s; reduced to {} in {:.1}s",
+        if search.resume.is_some() {
+            "Loaded"
+        } else {
+            "Generated"
+        },
+        thousands(generated),
+        generating.as_secs_f64(),
+        thousands(answerset.len()),
+        reducing.elapsed().as_secs_f64()
+    );
+
+    if search.verbose {
+        for (i, step) in stats.iter().enumerate() {
+            let att = &base[first + i];
+            let who = match att.id() {
+                Some(id) => format!("Student {}'s attempt", id),
+                None => format!("Attempt #{}", first + i + 1),
+            };
+            info!(
+                "{} (score {}) eliminated {} keys, leaving {}.",
+                who,
+                att.score(),
+                step.eliminated(),
+                step.after
+            );
+        }
     }
 
-    #[test]
-    fn permutations_checking() {
-        let att = QuizAttempt {
-            score: 1,
-            answers: vec![Answer::A, Answer::B],
-        };
-        let mut valid_keys: Vec<AnswerKey> = vec![];
+    if used < base.len() {
+        info!(
+            "The key was pinned down after {} of {} attempts.",
+            used,
+            base.len()
+        );
+    }
 
-        for key in [Answer::A, Answer::B, Answer::C, Answer::D]
+    status!(
+        "Found {} possible solutions! Writing to {}...",
+        answerset.len(),
+        search.output
+    );
+
+    if answerset.len() == 1 {
+        status!("The answer key is {}.", answerset.keys()[0].as_string());
+    }
+    warn_if_underdetermined(answerset.len(), search.warn_above);
+    let report = answerset.ambiguity_report();
+    if answerset.len() > 1 {
+        let ambiguous: Vec<String> = report
+            .ambiguous
             .iter()
-            .permutations(2)
-        {
-            let akey = AnswerKey::from(key.into_iter().copied().collect::<Vec<Answer>>());
-            if att.check(&akey) {
-                valid_keys.push(akey)
-            }
+            .map(|(q, options)| {
+                let options: Vec<String> = options.iter().map(|ans| ans.to_string()).collect();
+                let (last, rest) = options
+                    .split_last()
+                    .expect("Ambiguous questions have answers");
+                if rest.is_empty() {
+                    format!("Q{} ({})", q + 1, last)
+                } else {
+                    format!("Q{} ({} or {})", q + 1, rest.join(", "), last)
+                }
+            })
+            .collect();
+        status!(
+            "{}/{} questions resolved; ambiguous: {}",
+            report.resolved.len(),
+            len,
+            ambiguous.join(", ")
+        );
+        if let Some(best) = answerset.most_likely_key() {
+            status!("Most likely key: {}", best.as_string());
+        }
+        for (q, dist) in answerset.question_distribution().iter().enumerate() {
+            status!(
+                "  Q{}: {}",
+                q + 1,
+                dist.iter()
+                    .map(|(ans, p)| format!("{} {:.0}%", ans, p * 100.0))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            );
         }
+    }
+
+    #[cfg(feature = "serde")]
+    if let Some(path) = &search.report {
+        exit_on_write_error(path, report.save_to_json(path));
+    }
+    #[cfg(feature = "serde")]
+    if let Some(path) = &search.session_report {
+        let report = answerkey_search::SolveReport::new(&base, &choices, &stats, &answerset);
+        exit_on_write_error(path, report.save_to_json(path));
+    }
 
-        assert_eq!(valid_keys.len(), 4);
+    let found = answerset.len();
+    let answerset = match search.top_n {
+        Some(n) if n < found => {
+            info!("Writing only the {} most plausible keys.", n);
+            answerset.top_n(n)
+        }
+        _ => answerset,
+    };
 
-        let att2 = QuizAttempt {
-            score: 2,
-            answers: vec![Answer::A, Answer::B],
-        };
-        let mut valid_keys2: Vec<AnswerKey> = vec![];
-        for key2 in [Answer::A, Answer::B, Answer::C, Answer::D]
-            .iter()
-            .permutations(2)
-        {
-            let akey = AnswerKey::from(key2.into_iter().copied().collect::<Vec<Answer>>());
-            if att2.check(&akey) {
-                valid_keys2.push(akey)
-            }
+    let order = search.sort_order.clone().unwrap_or_default().0;
+    let answerset = answerset.sorted_by_order(&order);
+    let saved = match search.format {
+        OutputFormat::Text => answerset.save_to_file_in_order(
+            &search.output,
+            &[
+                format!("input: {}", source),
+                format!("attempts used: {} of {}", used, base.len()),
+                format!("quiz length: {}", len),
+                if answerset.len() < found {
+                    format!(
+                        "candidate keys: {} (top {} written)",
+                        found,
+                        answerset.len()
+                    )
+                } else {
+                    format!("candidate keys: {}", found)
+                },
+            ],
+            &order,
+        ),
+        OutputFormat::Csv => answerset.save_to_csv(&search.output),
+        #[cfg(feature = "serde")]
+        OutputFormat::Json => answerset.save_to_json(&search.output),
+        #[cfg(not(feature = "serde"))]
+        OutputFormat::Json => {
+            error!("JSON output needs answerkey-search to be built with the `serde` feature.");
+            process::exit(1);
         }
-        assert_eq!(valid_keys2.len(), 1);
+    };
+    exit_on_write_error(&search.output, saved);
+
+    wait_for_keypress(&args);
+    // by all the keys found, not only those written
+    if found > 1 {
+        process::exit(AMBIGUOUS);
     }
 }
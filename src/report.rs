@@ -0,0 +1,105 @@
+#[cfg(feature = "serde")]
+use std::io::{self, BufWriter, Write};
+
+#[cfg(feature = "serde")]
+use crate::key::create_output;
+use crate::{AnswerKeySet, Choices, QuizAttempt, ReduceStats, Score};
+
+/// One attempt as it went into a [`SolveReport`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ReportedAttempt {
+    pub id: Option<String>,
+    pub answers: String,
+    pub score: Score,
+}
+
+/// What went into a search and what came out of it, to keep alongside the
+/// grades it settled.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SolveReport {
+    /// In the order they were applied, the seed first.
+    pub attempts: Vec<ReportedAttempt>,
+    pub quiz_length: usize,
+    /// The letters allowed for each question.
+    pub choices: Vec<String>,
+    /// The effect of each attempt reduced by after the seed.
+    pub steps: Vec<ReduceStats>,
+    pub candidates: Vec<String>,
+    /// See [`AnswerKeySet::most_likely_key`].
+    pub best_guess: Option<String>,
+}
+
+impl SolveReport {
+    pub fn new(
+        attempts: &[QuizAttempt],
+        choices: &Choices,
+        steps: &[ReduceStats],
+        candidates: &AnswerKeySet,
+    ) -> SolveReport {
+        SolveReport {
+            attempts: attempts
+                .iter()
+                .map(|att| ReportedAttempt {
+                    id: att.id().map(str::to_string),
+                    answers: att.as_string(),
+                    score: att.score(),
+                })
+                .collect(),
+            quiz_length: choices.len(),
+            choices: (0..choices.len())
+                .map(|q| choices.at(q).iter().map(|ans| ans.to_string()).collect())
+                .collect(),
+            steps: steps.to_vec(),
+            candidates: candidates.to_strings(),
+            best_guess: candidates.most_likely_key().map(|key| key.as_string()),
+        }
+    }
+
+    /// Write the report as a JSON object to `filename`.
+    #[cfg(feature = "serde")]
+    pub fn save_to_json(&self, filename: &str) -> io::Result<()> {
+        let mut f = BufWriter::new(create_output(filename)?);
+
+        serde_json::to_writer_pretty(&mut f, self)?;
+        writeln!(f)?;
+        f.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{AnswerKeySet, Choices, QuizAttempt, SolveReport, DEFAULT_CHOICES};
+
+    #[test]
+    fn records_the_search() {
+        let attempts = vec![
+            QuizAttempt::from_string("ABC", 2.0).with_id("s1"),
+            QuizAttempt::from_string("ABD", 1.0),
+        ];
+        let choices = Choices::uniform(&DEFAULT_CHOICES, 3);
+        let (candidates, steps) = attempts[0]
+            .generate_valid_set(&choices)
+            .unwrap()
+            .reduce_until_unique_with_stats(&attempts[1..])
+            .unwrap();
+        let report = SolveReport::new(&attempts, &choices, &steps, &candidates);
+
+        assert_eq!(report.attempts[0].id.as_deref(), Some("s1"));
+        assert_eq!(report.attempts[1].answers, "ABD");
+        assert_eq!(report.quiz_length, 3);
+        assert_eq!(report.choices, vec!["ABCD"; 3]);
+        assert_eq!(report.steps[0].before, 9);
+        assert_eq!(report.candidates.len(), candidates.len());
+        assert!(report.best_guess.is_some());
+
+        let empty = SolveReport::new(&attempts, &choices, &[], &AnswerKeySet::from(vec![]));
+        assert_eq!(empty.best_guess, None);
+
+        #[cfg(feature = "serde")]
+        assert!(serde_json::to_string(&report)
+            .unwrap()
+            .contains(r#""steps":[{"before":9,"#));
+    }
+}
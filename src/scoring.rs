@@ -0,0 +1,117 @@
+pub type Score = f64;
+
+/// Scores and points are worked out in these fractions of a point, so sums
+/// of marks such as -0.25 or 0.1 are exact and compare equal. Anything finer
+/// than a ten-thousandth of a point is rounded to the nearest one.
+pub const POINT_UNITS: i64 = 10_000;
+
+fn to_units(points: Score) -> i64 {
+    (points * POINT_UNITS as Score).round() as i64
+}
+
+/// Points awarded for each correct, wrong and blank answer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScoringScheme {
+    pub correct: Score,
+    pub wrong: Score,
+    pub blank: Score,
+    /// How many times each question counts, all 1 if empty.
+    pub weights: Vec<i32>,
+}
+
+impl Default for ScoringScheme {
+    fn default() -> ScoringScheme {
+        ScoringScheme {
+            correct: 1.0,
+            wrong: 0.0,
+            blank: 0.0,
+            weights: vec![],
+        }
+    }
+}
+
+impl ScoringScheme {
+    pub fn is_weighted(&self) -> bool {
+        !self.weights.is_empty()
+    }
+
+    /// The weight of question `q`. Only meaningful once the weights have
+    /// been checked to be positive.
+    pub fn weight(&self, q: usize) -> usize {
+        self.weights.get(q).map_or(1, |&w| w as usize)
+    }
+
+    pub fn total(&self, correct: usize, wrong: usize, blank: usize) -> Score {
+        self.total_units(correct, wrong, blank) as Score / POINT_UNITS as Score
+    }
+
+    fn total_units(&self, correct: usize, wrong: usize, blank: usize) -> i64 {
+        to_units(self.correct) * correct as i64
+            + to_units(self.wrong) * wrong as i64
+            + to_units(self.blank) * blank as i64
+    }
+
+    /// How many of `answered` non-blank answers must be correct to total
+    /// `score`, if a whole number between 0 and `max` does. With weights, all
+    /// three counts are sums of weights.
+    pub fn correct_for(
+        &self,
+        score: Score,
+        answered: usize,
+        blank: usize,
+        max: usize,
+    ) -> Option<usize> {
+        let per_correct = to_units(self.correct) - to_units(self.wrong);
+        if per_correct == 0 {
+            return None;
+        }
+
+        let gained = to_units(score) - self.total_units(0, answered, blank);
+        if gained % per_correct != 0 {
+            return None;
+        }
+        let correct = gained / per_correct;
+        if correct < 0 || correct > max as i64 {
+            return None;
+        }
+
+        Some(correct as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ScoringScheme;
+
+    #[test]
+    fn correct_answers_from_penalty_score() {
+        let scheme = ScoringScheme {
+            correct: 1.0,
+            wrong: -0.25,
+            blank: 0.0,
+            weights: vec![],
+        };
+
+        // 7 right, 2 wrong, 1 blank
+        assert_eq!(scheme.total(7, 2, 1), 6.5);
+        assert_eq!(scheme.correct_for(6.5, 9, 1, 10), Some(7));
+        assert_eq!(scheme.correct_for(6.6, 9, 1, 10), None);
+        assert_eq!(ScoringScheme::default().correct_for(3.0, 4, 0, 4), Some(3));
+        assert_eq!(ScoringScheme::default().correct_for(5.0, 4, 0, 4), None);
+    }
+
+    #[test]
+    fn fractional_points_add_up_exactly() {
+        let scheme = ScoringScheme {
+            correct: 0.3,
+            wrong: -0.1,
+            blank: 0.0,
+            weights: vec![],
+        };
+
+        // 0.3 * 7 - 0.1 * 2 is 1.9000000000000001 in floating point
+        assert_eq!(scheme.total(7, 2, 0), 1.9);
+        assert_eq!(scheme.correct_for(1.9, 9, 0, 9), Some(7));
+        assert_eq!(scheme.correct_for(1.95, 9, 0, 9), None);
+    }
+}
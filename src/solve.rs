@@ -0,0 +1,515 @@
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+use crate::{Answer, AnswerKey, AnswerKeySet, Choices, QuizAttempt, SolveError, DEFAULT_CHOICES};
+
+/// The answers to questions settled by pairs of attempts differing at just
+/// that question.
+///
+/// Two attempts that give the same answers everywhere except at question `q`
+/// differ in score only through `q`. If one of them got exactly the weight
+/// of `q` more right than the other, it answered `q` correctly, and a blank
+/// is never right. Equal scores only say that neither answer is right, which
+/// does not settle `q`. Questions settled differently by different pairs are
+/// left `None`, the attempts contradict each other there. Attempts with a
+/// score tolerance settle nothing.
+pub fn infer_from_pairwise_diffs(attempts: &[QuizAttempt]) -> Vec<Option<Answer>> {
+    let len = attempts.first().map_or(0, |att| att.answers().len());
+    let mut inferred: Vec<Option<Answer>> = vec![None; len];
+    let mut conflicting = vec![false; len];
+
+    let exact: Vec<&QuizAttempt> = attempts.iter().filter(|att| att.tolerance() == 0).collect();
+    for (i, p) in exact.iter().enumerate() {
+        for r in &exact[i + 1..] {
+            let mut differing = (0..len).filter(|&q| p.answers()[q] != r.answers()[q]);
+            let q = match (differing.next(), differing.next()) {
+                (Some(q), None) => q,
+                _ => continue,
+            };
+
+            let weight = p.scheme().weight(q);
+            let right = if p.correct() == r.correct() + weight {
+                p.answers()[q]
+            } else if r.correct() == p.correct() + weight {
+                r.answers()[q]
+            } else {
+                continue;
+            };
+            if right == Answer::X {
+                continue;
+            }
+            match inferred[q] {
+                Some(ans) if ans != right => conflicting[q] = true,
+                _ => inferred[q] = Some(right),
+            }
+        }
+    }
+
+    for q in 0..len {
+        if conflicting[q] {
+            inferred[q] = None;
+        }
+    }
+    inferred
+}
+
+/// Narrow down the choices for each question using the attempts' scores,
+/// starting from the answers [`infer_from_pairwise_diffs`] settles.
+///
+/// An attempt can only have been right where its answer is still a possible
+/// choice. If exactly as many such positions remain as it got right, the
+/// attempt must have been right at all of them; if it is already known to be
+/// right at that many positions, it must have been wrong everywhere else.
+/// With weighted questions, positions are counted by their weights, and with
+/// a score tolerance by the fewest and most it could have got right.
+/// Returns `None` if the attempts contradict each other.
+pub fn narrow_choices(attempts: &[QuizAttempt], choices: &Choices) -> Option<Choices> {
+    let mut candidates: Vec<Vec<Answer>> =
+        (0..choices.len()).map(|q| choices.at(q).to_vec()).collect();
+
+    if attempts
+        .iter()
+        .all(|att| att.answers().len() == choices.len())
+    {
+        for (q, inferred) in infer_from_pairwise_diffs(attempts).into_iter().enumerate() {
+            if let Some(ans) = inferred {
+                if !candidates[q].contains(&ans) {
+                    return None;
+                }
+                candidates[q] = vec![ans];
+            }
+        }
+    }
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+
+        for att in attempts {
+            let possible: Vec<usize> = (0..candidates.len())
+                .filter(|&q| candidates[q].contains(&att.answers()[q]))
+                .collect();
+            let reachable: usize = possible.iter().map(|&q| att.scheme().weight(q)).sum();
+            let forced: usize = possible
+                .iter()
+                .filter(|&&q| candidates[q].len() == 1)
+                .map(|&q| att.scheme().weight(q))
+                .sum();
+
+            let correct = att.correct_range();
+            if reachable < *correct.start() || forced > *correct.end() {
+                return None;
+            }
+
+            if reachable == *correct.start() {
+                for &q in &possible {
+                    if candidates[q].len() > 1 {
+                        candidates[q] = vec![att.answers()[q]];
+                        changed = true;
+                    }
+                }
+            } else if forced == *correct.end() {
+                for &q in &possible {
+                    if candidates[q].len() > 1 {
+                        candidates[q].retain(|&ans| ans != att.answers()[q]);
+                        changed = true;
+                    }
+                }
+            }
+        }
+    }
+
+    Some(Choices::per_question(candidates))
+}
+
+struct Search<'a> {
+    attempts: &'a [QuizAttempt],
+    candidates: &'a Choices,
+    // possible[a][q]: attempt a could still be right at question q
+    possible: Vec<Vec<bool>>,
+    // matched and remaining weight of each attempt
+    matched: Vec<usize>,
+    remaining: Vec<usize>,
+    key: Vec<Answer>,
+    found: Vec<AnswerKey>,
+}
+
+impl Search<'_> {
+    fn run(&mut self, q: usize) {
+        if q == self.key.len() {
+            self.found.push(AnswerKey::from(self.key.clone()));
+            return;
+        }
+
+        for &ans in self.candidates.at(q) {
+            for (a, att) in self.attempts.iter().enumerate() {
+                let weight = att.scheme().weight(q);
+                self.remaining[a] -= self.possible[a][q] as usize * weight;
+                self.matched[a] += (att.answers()[q] == ans) as usize * weight;
+            }
+
+            let feasible = self.attempts.iter().enumerate().all(|(a, att)| {
+                let correct = att.correct_range();
+                self.matched[a] <= *correct.end()
+                    && self.matched[a] + self.remaining[a] >= *correct.start()
+            });
+            if feasible {
+                self.key[q] = ans;
+                self.run(q + 1);
+            }
+
+            for (a, att) in self.attempts.iter().enumerate() {
+                let weight = att.scheme().weight(q);
+                self.remaining[a] += self.possible[a][q] as usize * weight;
+                self.matched[a] -= (att.answers()[q] == ans) as usize * weight;
+            }
+        }
+    }
+}
+
+/// Find every key consistent with all `attempts` by narrowing the choices
+/// with [`narrow_choices`] and then searching only what is left, abandoning a
+/// partial key as soon as some attempt can no longer reach its score.
+pub fn solve_constrained(attempts: &[QuizAttempt], choices: &Choices) -> AnswerKeySet {
+    if attempts
+        .iter()
+        .any(|att| att.answers().len() != choices.len())
+    {
+        panic!("Unmatched lengths!");
+    }
+
+    let candidates = match narrow_choices(attempts, choices) {
+        Some(candidates) => candidates,
+        None => return AnswerKeySet::from(vec![]),
+    };
+
+    let possible: Vec<Vec<bool>> = attempts
+        .iter()
+        .map(|att| {
+            (0..candidates.len())
+                .map(|q| candidates.at(q).contains(&att.answers()[q]))
+                .collect()
+        })
+        .collect();
+
+    let mut search = Search {
+        attempts,
+        candidates: &candidates,
+        remaining: attempts
+            .iter()
+            .zip(&possible)
+            .map(|(att, p)| {
+                (0..p.len())
+                    .filter(|&q| p[q])
+                    .map(|q| att.scheme().weight(q))
+                    .sum()
+            })
+            .collect(),
+        possible,
+        matched: vec![0; attempts.len()],
+        key: vec![Answer::X; candidates.len()],
+        found: vec![],
+    };
+    search.run(0);
+
+    let mut keys = search.found;
+    keys.sort();
+    AnswerKeySet::from(keys)
+}
+
+// Candidate keys worth generating outright instead of searching. In the
+// `strategy` bench in benches/search.rs the search never lost, but the gap
+// only matters past here: 40 vs 28 µs at 135 keys and 80 vs 55 µs at 252,
+// then 1.2 vs 0.6 ms at 3,240 and 82 vs 35 ms at 147,420. Below 1,000 keys
+// both take a fraction of a millisecond.
+const GENERATE_LIMIT: u128 = 1_000;
+
+/// Find every key consistent with all `attempts` with the default choices,
+/// see [`solve_with`].
+pub fn solve(attempts: &[QuizAttempt]) -> Result<AnswerKeySet, SolveError> {
+    let len = attempts.first().map_or(0, |att| att.answers().len());
+    solve_with(attempts, &Choices::uniform(&DEFAULT_CHOICES, len))
+}
+
+/// Find every key consistent with all `attempts`, choosing how. The choices
+/// are narrowed first, which often pins down the key by itself; otherwise
+/// the keys are generated from [`QuizAttempt::best_seed_with`] and reduced
+/// when that seed allows few enough of them, or searched for with
+/// [`solve_constrained`]. To run those stages by hand, such as to reduce the
+/// keys of one seed by different attempts, see
+/// [`QuizAttempt::generate_valid_set`] and [`AnswerKeySet::reduced_by`].
+pub fn solve_with(attempts: &[QuizAttempt], choices: &Choices) -> Result<AnswerKeySet, SolveError> {
+    let narrowed = match narrow_choices(attempts, choices) {
+        Some(narrowed) => narrowed,
+        None => return Ok(AnswerKeySet::from(vec![])),
+    };
+
+    if (0..narrowed.len()).all(|q| narrowed.at(q).len() == 1) {
+        let key: Vec<Answer> = (0..narrowed.len()).map(|q| narrowed.at(q)[0]).collect();
+        return Ok(AnswerKeySet::from(vec![AnswerKey::from(key)]));
+    }
+
+    if !attempts.is_empty() {
+        let seed = QuizAttempt::best_seed_with(attempts, &narrowed);
+        if seed.estimate_candidates_with(&narrowed) <= GENERATE_LIMIT {
+            return Ok(attempts
+                .iter()
+                .fold(seed.generate_valid_set(&narrowed)?, |set, att| {
+                    set.reduce(att)
+                }));
+        }
+    }
+    Ok(solve_constrained(attempts, &narrowed))
+}
+
+/// Find every key consistent with all `attempts` by generating the keys of
+/// each of the `k` highest scorers independently, intersecting them and
+/// reducing what is left with the remaining attempts. This agrees with
+/// [`solve_with`], so it doubles as a cross-check of it.
+pub fn solve_by_intersection(
+    attempts: &[QuizAttempt],
+    choices: &Choices,
+    k: usize,
+) -> Result<AnswerKeySet, SolveError> {
+    if attempts.is_empty() {
+        return Ok(solve_constrained(attempts, choices));
+    }
+
+    let mut order: Vec<usize> = (0..attempts.len()).collect();
+    order.sort_by_key(|&a| std::cmp::Reverse(attempts[a].correct()));
+    let (seeds, rest) = order.split_at(k.clamp(1, attempts.len()));
+
+    #[cfg(not(feature = "rayon"))]
+    let sets: Vec<AnswerKeySet> = seeds
+        .iter()
+        .map(|&a| attempts[a].generate_valid_set(choices))
+        .collect::<Result<_, _>>()?;
+    #[cfg(feature = "rayon")]
+    let sets: Vec<AnswerKeySet> = seeds
+        .par_iter()
+        .map(|&a| attempts[a].generate_valid_set(choices))
+        .collect::<Result<_, _>>()?;
+
+    let mut sets = sets.into_iter();
+    let first = sets.next().unwrap();
+    let intersection = sets.fold(first, |set, other| set.intersect(&other));
+    Ok(rest
+        .iter()
+        .fold(intersection, |set, &a| set.reduce(&attempts[a])))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        infer_from_pairwise_diffs, narrow_choices, solve, solve_by_intersection, solve_constrained,
+        solve_with, Answer, Choices, QuizAttempt, ScoringScheme, DEFAULT_CHOICES,
+    };
+
+    fn attempts(lines: &[(&str, f64)]) -> Vec<QuizAttempt> {
+        lines
+            .iter()
+            .map(|&(answers, score)| QuizAttempt::from_string(answers, score))
+            .collect()
+    }
+
+    #[test]
+    fn narrowing_fixes_and_removes_answers() {
+        let atts = attempts(&[("AB", 0.0), ("CD", 2.0)]);
+        let narrowed = narrow_choices(&atts, &Choices::uniform(&DEFAULT_CHOICES, 2)).unwrap();
+
+        assert_eq!(narrowed.at(0), &[Answer::C]);
+        assert_eq!(narrowed.at(1), &[Answer::D]);
+
+        let atts = attempts(&[("AB", 0.0), ("AB", 1.0)]);
+        assert_eq!(
+            narrow_choices(&atts, &Choices::uniform(&DEFAULT_CHOICES, 2)),
+            None
+        );
+    }
+
+    #[test]
+    fn pairs_differing_once_settle_a_question() {
+        let atts = attempts(&[("ABCD", 2.0), ("ABDD", 1.0), ("CBCA", 1.0), ("CBCX", 1.0)]);
+        assert_eq!(
+            infer_from_pairwise_diffs(&atts),
+            vec![None, None, Some(Answer::C), None]
+        );
+        // the scores of each attempt alone settle nothing
+        let choices = Choices::uniform(&DEFAULT_CHOICES, 4);
+        let alone: Vec<usize> = (0..atts.len())
+            .map(|a| {
+                let narrowed = narrow_choices(&atts[a..=a], &choices).unwrap();
+                (0..4).filter(|&q| narrowed.at(q).len() == 1).count()
+            })
+            .collect();
+        assert_eq!(alone, vec![0; 4]);
+        assert_eq!(narrow_choices(&atts, &choices).unwrap().at(2), &[Answer::C]);
+
+        // either answer of a pair with equal scores may be wrong
+        let atts = attempts(&[("AB", 1.0), ("AC", 1.0)]);
+        assert_eq!(infer_from_pairwise_diffs(&atts), vec![None, None]);
+
+        let atts = attempts(&[("AB", 1.0), ("AC", 0.0), ("DB", 0.0), ("DC", 1.0)]);
+        assert_eq!(infer_from_pairwise_diffs(&atts)[1], None);
+    }
+
+    #[test]
+    fn matches_brute_force() {
+        let atts = attempts(&[
+            ("ABCD", 3.0),
+            ("ACCD", 3.0),
+            ("ABCA", 2.0),
+            ("ABDD", 2.0),
+            ("XBCX", 1.0),
+        ]);
+        let choices = Choices::uniform(&DEFAULT_CHOICES, 4);
+
+        let brute = atts[1..]
+            .iter()
+            .fold(atts[0].generate_valid_set(&choices).unwrap(), |set, att| {
+                set.reduce(att)
+            });
+        let constrained = solve_constrained(&atts, &choices);
+
+        assert_eq!(constrained.keys(), brute.keys());
+        assert!(!constrained.keys().is_empty());
+        assert_eq!(solve_with(&atts, &choices).unwrap().keys(), brute.keys());
+        assert_eq!(solve(&atts).unwrap().keys(), brute.keys());
+        for k in [0, 1, 3, 10] {
+            assert_eq!(
+                solve_by_intersection(&atts, &choices, k).unwrap().keys(),
+                brute.keys()
+            );
+        }
+    }
+
+    #[test]
+    fn tolerant_scores_match_brute_force() {
+        let atts: Vec<QuizAttempt> =
+            attempts(&[("ABCD", 3.0), ("ACCD", 4.0), ("ABCA", 2.0), ("DBDD", 0.0)])
+                .into_iter()
+                .map(|att| att.with_tolerance(1))
+                .collect();
+        let choices = Choices::uniform(&DEFAULT_CHOICES, 4);
+
+        // a blank attempt scoring 0 allows every key
+        let every = QuizAttempt::from_string("XXXX", 0.0)
+            .generate_valid_set(&choices)
+            .unwrap();
+        let brute: Vec<_> = every
+            .iter()
+            .filter(|key| key.consistent_with(&atts))
+            .cloned()
+            .collect();
+
+        assert!(brute.len() > 1);
+        assert_eq!(solve_constrained(&atts, &choices).keys(), brute);
+        assert_eq!(solve_with(&atts, &choices).unwrap().keys(), brute);
+        let generated = atts
+            .iter()
+            .fold(atts[0].generate_valid_set(&choices).unwrap(), |set, att| {
+                set.reduce(att)
+            });
+        assert_eq!(generated.keys(), brute);
+    }
+
+    #[test]
+    fn solve_picks_a_strategy() {
+        let choices = Choices::uniform(&DEFAULT_CHOICES, 4);
+
+        // narrowing alone fixes every question
+        let atts = attempts(&[("ABCD", 0.0), ("BCDA", 0.0), ("CDAB", 0.0), ("DCBA", 2.0)]);
+        assert_eq!(solve_with(&atts, &choices).unwrap().to_string(), "DABC");
+
+        // too many keys to generate from any one attempt of 12 questions
+        let atts = attempts(&[
+            ("ABCDABCDABCD", 6.0),
+            ("BBCCAADDABCD", 5.0),
+            ("DCBADCBADCBA", 3.0),
+        ]);
+        let choices = Choices::uniform(&DEFAULT_CHOICES, 12);
+        let seed = QuizAttempt::best_seed_with(&atts, &choices);
+        assert!(seed.estimate_candidates_with(&choices) > super::GENERATE_LIMIT);
+        let solved = solve_with(&atts, &choices).unwrap();
+        assert_eq!(solved.keys(), solve_constrained(&atts, &choices).keys());
+        assert!(solved.iter().all(|key| key.consistent_with(&atts)));
+
+        let contradiction = attempts(&[("AB", 0.0), ("AB", 1.0)]);
+        assert!(
+            solve_with(&contradiction, &Choices::uniform(&DEFAULT_CHOICES, 2))
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn weighted_matches_brute_force() {
+        let scheme = ScoringScheme {
+            weights: vec![1, 2, 3, 1],
+            ..ScoringScheme::default()
+        };
+        let atts: Vec<QuizAttempt> = [("ABCD", 4.0), ("ACCA", 6.0), ("DCBB", 3.0)]
+            .iter()
+            .map(|&(answers, score)| {
+                QuizAttempt::try_from_string_with(answers, score, scheme.clone()).unwrap()
+            })
+            .collect();
+        let choices = Choices::uniform(&DEFAULT_CHOICES, 4);
+
+        let brute = atts[1..]
+            .iter()
+            .fold(atts[0].generate_valid_set(&choices).unwrap(), |set, att| {
+                set.reduce(att)
+            });
+
+        assert_eq!(solve_constrained(&atts, &choices).keys(), brute.keys());
+        assert!(brute.keys().contains(&"ACCB".parse().unwrap()));
+    }
+
+    mod prop {
+        use proptest::prelude::*;
+
+        use crate::{solve_with, AnswerKey, Choices, QuizAttempt, DEFAULT_CHOICES};
+
+        // a true key and attempts at it as strings, blanks included
+        fn quiz() -> impl Strategy<Value = (String, Vec<String>)> {
+            (1..8usize).prop_flat_map(|len| {
+                (
+                    proptest::string::string_regex(&format!("[ABCD]{{{len}}}")).unwrap(),
+                    proptest::collection::vec(
+                        proptest::string::string_regex(&format!("[ABCDX]{{{len}}}")).unwrap(),
+                        1..5,
+                    ),
+                )
+            })
+        }
+
+        proptest! {
+            #[test]
+            fn solve_finds_the_true_key((key, answers) in quiz()) {
+                let key: AnswerKey = key.parse().unwrap();
+                let atts: Vec<QuizAttempt> = answers
+                    .iter()
+                    .map(|answers| {
+                        let right = answers
+                            .chars()
+                            .zip(key.as_string().chars())
+                            .filter(|(a, k)| a == k)
+                            .count();
+                        QuizAttempt::from_string(answers, right as f64)
+                    })
+                    .collect();
+                let choices = Choices::uniform(&DEFAULT_CHOICES, key.answers().len());
+
+                let solved = solve_with(&atts, &choices).unwrap();
+                prop_assert!(solved.keys().contains(&key));
+                prop_assert!(solved.iter().all(|key| key.consistent_with(&atts)));
+
+                let reduced = atts
+                    .iter()
+                    .fold(atts[0].generate_valid_set(&choices).unwrap(), |set, att| set.reduce(att));
+                prop_assert_eq!(reduced.keys(), solved.keys());
+            }
+        }
+    }
+}
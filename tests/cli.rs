@@ -0,0 +1,98 @@
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+// a file of this test's own in the temporary directory
+fn temp_file(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("answerkey-cli-{}-{}", std::process::id(), name))
+}
+
+// search the attempts in `input`, writing to a temporary output file
+fn run(name: &str, input: &str, args: &[&str]) -> (Output, String) {
+    let (input_path, output_path) = (temp_file(&format!("{}.txt", name)), temp_file(name));
+    fs::write(&input_path, input).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_answerkey-search"))
+        .arg(&input_path)
+        .args(["--no-wait", "-q", "-o"])
+        .arg(&output_path)
+        .args(args)
+        .output()
+        .unwrap();
+    let written = fs::read_to_string(&output_path).unwrap_or_default();
+    let _ = fs::remove_file(&input_path);
+    let _ = fs::remove_file(&output_path);
+    (output, written)
+}
+
+#[test]
+fn top_n_keeps_the_ambiguous_status() {
+    let (output, written) = run("top-n", "ABCD,3\n", &["--top-n", "1"]);
+
+    assert_eq!(written.lines().filter(|l| !l.starts_with('#')).count(), 1);
+    assert!(written.contains("candidate keys: 12 (top 1 written)"));
+    assert_eq!(output.status.code(), Some(2));
+
+    let (output, _) = run("unique", "ABCD,4\n", &["--top-n", "1"]);
+    assert_eq!(output.status.code(), Some(0));
+}
+
+#[test]
+fn usage_errors_exit_as_bad_input() {
+    let (output, _) = run("usage", "ABCD,3\n", &["--no-such-option"]);
+    assert_eq!(output.status.code(), Some(1));
+
+    let (output, _) = run("search-before-verify", "ABCD,3\n", &["verify", "ABCD"]);
+    assert_eq!(output.status.code(), Some(1));
+
+    // stream mode does not sort or report on the reduction
+    for ignored in [
+        &["--stream", "--sort-order", "DCBA"][..],
+        &["--stream", "--verbose"],
+    ] {
+        let (output, _) = run("stream-conflicts", "ABCD,3\n", ignored);
+        assert_eq!(output.status.code(), Some(1));
+    }
+}
+
+#[test]
+fn rejected_key_is_a_contradiction() {
+    let input = temp_file("verify.txt");
+    fs::write(&input, "ABCD,3\n").unwrap();
+    let verify = |key: &str| {
+        Command::new(env!("CARGO_BIN_EXE_answerkey-search"))
+            .args(["verify", key])
+            .arg(&input)
+            .args(["--no-wait", "-q"])
+            .output()
+            .unwrap()
+    };
+
+    assert_eq!(verify("ABCA").status.code(), Some(0));
+    assert_eq!(verify("ABCD").status.code(), Some(3));
+    assert_eq!(verify("ABC").status.code(), Some(1));
+    fs::remove_file(&input).unwrap();
+}
+
+#[test]
+fn resumed_key_is_checked_against_every_attempt() {
+    let saved = temp_file("saved.txt");
+    fs::write(&saved, "ABCD\n").unwrap();
+    let resume = ["--resume", saved.to_str().unwrap()];
+
+    let (output, _) = run("resume-consistent", "ABCD,4\nABCA,3\n", &resume);
+    assert_eq!(output.status.code(), Some(0));
+    let (output, _) = run("resume-contradicted", "ABCD,4\nABCA,1\n", &resume);
+    fs::remove_file(&saved).unwrap();
+    assert_eq!(output.status.code(), Some(3));
+}
+
+#[test]
+fn known_answers_number_questions_from_one() {
+    let (output, written) = run("known-first", "ABCD,3\n", &["--known", "1=B"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(written.lines().any(|l| l == "BBCD"));
+
+    let (output, _) = run("known-zero", "ABCD,3\n", &["--known", "0=B"]);
+    assert_eq!(output.status.code(), Some(1));
+}